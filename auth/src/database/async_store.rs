@@ -0,0 +1,184 @@
+use crate::person::Person;
+use crate::user::AuthenticatedUser;
+use std::cell::Cell;
+use std::rc::Rc;
+use yew::services::fetch::FetchTask;
+use yew::Callback;
+
+use super::error::{DbError, DbResult};
+use super::query::{PersonsPage, PersonsQuery};
+use super::store::LoggedIn;
+use super::DbConn;
+
+/// A request in progress, dropping it cancels the request and its
+/// callback is not called anymore
+#[must_use]
+#[derive(Default)]
+pub struct Pending {
+    _tasks: Vec<FetchTask>,
+    active: Rc<Cell<bool>>,
+}
+
+impl Pending {
+    /// A request that was already answered
+    pub fn done() -> Self {
+        Self::default()
+    }
+
+    pub(super) fn new(tasks: Vec<FetchTask>, active: Rc<Cell<bool>>) -> Self {
+        Self {
+            _tasks: tasks,
+            active,
+        }
+    }
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        // Aborted fetches still answer, with an error the page must not see
+        self.active.set(false);
+    }
+}
+
+/// The operations of the login, persons list and person pages, answered
+/// through callbacks so the data can live on a server as well as in the
+/// browser. Failures reaching the data are `DbError::Storage` and can be
+/// retried
+pub trait AsyncStore {
+    /// Whether the data lives on a server, the operations of `Store` not
+    /// found here are then unavailable
+    fn is_remote(&self) -> bool;
+
+    fn log_in(
+        &self,
+        username: &str,
+        password: &str,
+        callback: Callback<DbResult<LoggedIn>>,
+    ) -> Pending;
+
+    /// Acts as the user of a session that survived a reload
+    fn resume(
+        &self,
+        username: &str,
+        token: &str,
+        callback: Callback<DbResult<AuthenticatedUser>>,
+    ) -> Pending;
+
+    fn log_out(&self);
+
+    fn query_persons(
+        &self,
+        query: &PersonsQuery,
+        callback: Callback<DbResult<PersonsPage>>,
+    ) -> Pending;
+
+    fn get_person(&self, id: u32, callback: Callback<DbResult<Person>>) -> Pending;
+
+    /// Stores a new person, answering with the id it was assigned
+    fn insert(&self, person: Person, callback: Callback<DbResult<u32>>) -> Pending;
+
+    fn update(&self, person: Person, callback: Callback<DbResult<()>>) -> Pending;
+
+    /// Moves the persons to the trash, given as their ids along with the
+    /// version they were read at, answering with the outcome for each of
+    /// them once every one was handled
+    fn delete(&self, persons: Vec<(u32, u32)>, callback: Callback<Vec<DbResult<()>>>) -> Pending;
+}
+
+pub type AsyncConn = Rc<dyn AsyncStore>;
+
+/// Answers right away from a `Store` in the browser
+#[derive(Debug)]
+pub struct LocalStore {
+    db_conn: DbConn,
+}
+
+impl LocalStore {
+    pub fn new(db_conn: DbConn) -> Self {
+        Self { db_conn }
+    }
+}
+
+impl AsyncStore for LocalStore {
+    fn is_remote(&self) -> bool {
+        false
+    }
+
+    fn log_in(
+        &self,
+        username: &str,
+        password: &str,
+        callback: Callback<DbResult<LoggedIn>>,
+    ) -> Pending {
+        callback.emit(self.db_conn.borrow_mut().log_in(username, password));
+
+        Pending::done()
+    }
+
+    fn resume(
+        &self,
+        username: &str,
+        token: &str,
+        callback: Callback<DbResult<AuthenticatedUser>>,
+    ) -> Pending {
+        callback.emit(self.db_conn.borrow_mut().resume(username, token));
+
+        Pending::done()
+    }
+
+    fn log_out(&self) {
+        self.db_conn.borrow_mut().log_out();
+    }
+
+    fn query_persons(
+        &self,
+        query: &PersonsQuery,
+        callback: Callback<DbResult<PersonsPage>>,
+    ) -> Pending {
+        callback.emit(Ok(self.db_conn.borrow().query_persons(query)));
+
+        Pending::done()
+    }
+
+    fn get_person(&self, id: u32, callback: Callback<DbResult<Person>>) -> Pending {
+        let person = self.db_conn.borrow().get_person_by_id(id);
+
+        callback.emit(person.ok_or_else(|| DbError::NotFound(format!("Person with ID {}", id))));
+
+        Pending::done()
+    }
+
+    fn insert(&self, person: Person, callback: Callback<DbResult<u32>>) -> Pending {
+        callback.emit(self.db_conn.borrow_mut().insert(person));
+
+        Pending::done()
+    }
+
+    fn update(&self, person: Person, callback: Callback<DbResult<()>>) -> Pending {
+        callback.emit(self.db_conn.borrow_mut().update(person));
+
+        Pending::done()
+    }
+
+    fn delete(&self, persons: Vec<(u32, u32)>, callback: Callback<Vec<DbResult<()>>>) -> Pending {
+        let results = {
+            let mut db = self.db_conn.borrow_mut();
+
+            // Deleted all at once, so they are also undone at once
+            db.begin_batch();
+
+            let results = persons
+                .into_iter()
+                .map(|(id, version)| db.delete(id, Some(version)))
+                .collect();
+
+            db.end_batch();
+
+            results
+        };
+
+        callback.emit(results);
+
+        Pending::done()
+    }
+}
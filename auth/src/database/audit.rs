@@ -0,0 +1,85 @@
+use crate::person::{Person, PersonField};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AuditOperation {
+    Insert,
+    Update,
+    Delete,
+    Restore,
+    Purge,
+}
+
+impl AuditOperation {
+    pub fn label(self) -> &'static str {
+        match self {
+            AuditOperation::Insert => "Insert",
+            AuditOperation::Update => "Update",
+            AuditOperation::Delete => "Delete",
+            AuditOperation::Restore => "Restore",
+            AuditOperation::Purge => "Purge",
+        }
+    }
+}
+
+/// A change made to a person, `before` is `None` for inserts and restores
+/// and `after` is `None` for deletes and purges
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    pub username: String,
+    pub operation: AuditOperation,
+    pub person_id: u32,
+    pub before: Option<Person>,
+    pub after: Option<Person>,
+}
+
+impl AuditEntry {
+    /// Fields that differ between `before` and `after`, along with their
+    /// old and new values
+    pub fn changes(&self) -> Vec<(PersonField, String, String)> {
+        let value = |person: &Option<Person>, field| {
+            person
+                .as_ref()
+                .map(|person| person.field_value(field))
+                .unwrap_or_default()
+        };
+
+        PersonField::ALL
+            .iter()
+            .map(|field| {
+                (
+                    *field,
+                    value(&self.before, *field),
+                    value(&self.after, *field),
+                )
+            })
+            .filter(|(_, before, after)| before != after)
+            .collect()
+    }
+}
+
+/// Filters over the audit log, every filter that is set must match
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AuditQuery {
+    pub username: Option<String>,
+    pub person_id: Option<u32>,
+    /// First local date of the entries, inclusive
+    pub from: Option<NaiveDate>,
+    /// Last local date of the entries, inclusive
+    pub to: Option<NaiveDate>,
+}
+
+impl AuditQuery {
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        let date = entry.timestamp.with_timezone(&Local).date_naive();
+
+        self.username
+            .as_ref()
+            .is_none_or(|username| entry.username == *username)
+            && self.person_id.is_none_or(|id| entry.person_id == id)
+            && self.from.is_none_or(|from| date >= from)
+            && self.to.is_none_or(|to| date <= to)
+    }
+}
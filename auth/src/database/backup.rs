@@ -0,0 +1,86 @@
+use crate::person::Person;
+use crate::user::User;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::error::{DbError, DbResult};
+use super::trash::DeletedPerson;
+
+/// Version of the backup format written by this build, backups of newer
+/// versions are rejected instead of being read partially
+pub const BACKUP_VERSION: u32 = 1;
+
+/// The whole data of a `Database` as a single JSON document, users only
+/// carry the hashes of their passwords
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Backup {
+    pub version: u32,
+    pub created_at: DateTime<Utc>,
+    pub created_by: String,
+    /// Id handed to the next inserted person
+    pub next_id: u32,
+    pub retention_days: u32,
+    pub persons: Vec<Person>,
+    pub trash: Vec<DeletedPerson>,
+    pub users: Vec<User>,
+}
+
+impl Backup {
+    pub fn to_json(&self) -> DbResult<String> {
+        serde_json::to_string_pretty(self).map_err(|err| DbError::Storage(err.to_string()))
+    }
+
+    /// Reads a backup, the format version is checked before anything else
+    pub fn from_json(json: &str) -> DbResult<Backup> {
+        #[derive(Deserialize)]
+        struct Header {
+            version: u32,
+        }
+
+        let invalid =
+            |err: serde_json::Error| DbError::Validation(format!("Not a backup: {}", err));
+        let header: Header = serde_json::from_str(json).map_err(invalid)?;
+
+        if header.version > BACKUP_VERSION {
+            return Err(DbError::Validation(format!(
+                "The backup has format version {}, this version only reads up to {}",
+                header.version, BACKUP_VERSION
+            )));
+        }
+
+        serde_json::from_str(json).map_err(invalid)
+    }
+}
+
+/// How a backup is restored
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RestoreMode {
+    /// The backup becomes the whole data, anything missing from it is lost
+    Replace,
+    /// Persons and users of the backup are added, or overwrite the ones with
+    /// the same id or username, and everything else is kept
+    Merge,
+}
+
+/// Differences between the current data and the data a restore would leave
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BackupDiff {
+    pub added_persons: Vec<Person>,
+    /// Stored persons along with the version from the backup
+    pub changed_persons: Vec<(Person, Person)>,
+    pub removed_persons: Vec<Person>,
+    pub added_users: Vec<String>,
+    pub changed_users: Vec<String>,
+    pub removed_users: Vec<String>,
+}
+
+impl BackupDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_persons.is_empty()
+            && self.changed_persons.is_empty()
+            && self.removed_persons.is_empty()
+            && self.added_users.is_empty()
+            && self.changed_users.is_empty()
+            && self.removed_users.is_empty()
+    }
+}
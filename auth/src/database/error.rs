@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+
+use crate::person::Person;
+
+pub type DbResult<T> = Result<T, DbError>;
+
+/// Reasons a `Database` operation can fail
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DbError {
+    /// No record matches the given key
+    NotFound(String),
+    /// A record with the same key already exists
+    Duplicate(String),
+    /// The record doesn't satisfy the rules of the `Database`
+    Validation(String),
+    /// The credentials don't match any enabled user
+    Authentication(String),
+    /// The current user is not allowed to perform the operation
+    PermissionDenied(String),
+    /// The backend failed to read or write the data
+    Storage(String),
+    /// The person was changed since it was read, holds the stored one
+    Conflict(Box<Person>),
+}
+
+impl fmt::Display for DbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbError::NotFound(what) => write!(f, "{} not found", what),
+            DbError::Duplicate(what) => write!(f, "{} already exists", what),
+            DbError::Validation(reason) => write!(f, "Invalid data: {}", reason),
+            DbError::Authentication(reason) => write!(f, "{}", reason),
+            DbError::PermissionDenied(action) => write!(f, "You are not allowed to {}", action),
+            DbError::Storage(reason) => write!(f, "Storage error: {}", reason),
+            DbError::Conflict(stored) => write!(
+                f,
+                "Person with ID {} was changed by someone else in the meantime",
+                stored.id
+            ),
+        }
+    }
+}
+
+impl Error for DbError {}
@@ -0,0 +1,128 @@
+use crate::person::Person;
+use std::mem;
+
+use super::audit::AuditOperation;
+
+/// State of a person before and after a change, `None` when it wasn't
+/// among the live persons
+#[derive(Debug, Clone, PartialEq)]
+pub struct PersonChange {
+    pub operation: AuditOperation,
+    pub id: u32,
+    pub before: Option<Person>,
+    pub after: Option<Person>,
+}
+
+impl PersonChange {
+    /// The change that reverts this one, purges can't be reverted
+    pub fn inverse(&self) -> PersonChange {
+        PersonChange {
+            operation: match self.operation {
+                AuditOperation::Insert | AuditOperation::Restore => AuditOperation::Delete,
+                AuditOperation::Delete | AuditOperation::Purge => AuditOperation::Restore,
+                AuditOperation::Update => AuditOperation::Update,
+            },
+            id: self.id,
+            before: self.after.clone(),
+            after: self.before.clone(),
+        }
+    }
+}
+
+/// Short description of a group of changes, like "delete of 3 persons"
+pub fn describe(changes: &[PersonChange]) -> String {
+    let operation = match changes.first() {
+        Some(first) if changes.iter().all(|c| c.operation == first.operation) => {
+            first.operation.label().to_lowercase()
+        }
+        _ => "change".to_string(),
+    };
+
+    match changes {
+        [change] => {
+            let name = change
+                .after
+                .as_ref()
+                .or(change.before.as_ref())
+                .map(|person| person.name.as_str())
+                .unwrap_or_default();
+
+            format!("{} of {}", operation, name)
+        }
+        _ => format!("{} of {} persons", operation, changes.len()),
+    }
+}
+
+/// Changes made to the persons that can be undone and redone, the changes
+/// made within a batch are undone and redone together
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    undo: Vec<Vec<PersonChange>>,
+    redo: Vec<Vec<PersonChange>>,
+    pending: Vec<PersonChange>,
+    batch_depth: usize,
+}
+
+impl History {
+    /// Adds a new change, which drops whatever could be redone
+    pub fn record(&mut self, change: PersonChange) {
+        self.pending.push(change);
+        self.commit();
+    }
+
+    pub fn begin_batch(&mut self) {
+        self.batch_depth += 1;
+    }
+
+    pub fn end_batch(&mut self) {
+        self.batch_depth = self.batch_depth.saturating_sub(1);
+        self.commit();
+    }
+
+    fn commit(&mut self) {
+        if self.batch_depth == 0 && !self.pending.is_empty() {
+            self.undo.push(mem::take(&mut self.pending));
+            self.redo.clear();
+        }
+    }
+
+    pub fn next_undo(&self) -> Option<&[PersonChange]> {
+        self.undo.last().map(Vec::as_slice)
+    }
+
+    pub fn next_redo(&self) -> Option<&[PersonChange]> {
+        self.redo.last().map(Vec::as_slice)
+    }
+
+    /// Moves the last changes to the redo stack, returning them
+    pub fn undone(&mut self) -> Option<Vec<PersonChange>> {
+        let changes = self.undo.pop()?;
+
+        self.redo.push(changes.clone());
+
+        Some(changes)
+    }
+
+    /// Moves the last undone changes back to the undo stack, returning them
+    pub fn redone(&mut self) -> Option<Vec<PersonChange>> {
+        let changes = self.redo.pop()?;
+
+        self.undo.push(changes.clone());
+
+        Some(changes)
+    }
+
+    /// Drops every step involving the person `id`, once it can't be
+    /// brought back
+    pub fn forget(&mut self, id: u32) {
+        let keep = |changes: &Vec<PersonChange>| changes.iter().all(|c| c.id != id);
+
+        self.undo.retain(keep);
+        self.redo.retain(keep);
+        self.pending.retain(|c| c.id != id);
+    }
+
+    pub fn clear(&mut self) {
+        *self = History::default();
+    }
+}
@@ -0,0 +1,440 @@
+use crate::person::Person;
+use crate::query_string::{decode, encode};
+use crate::user::AuthenticatedUser;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use yew::format::{Json, Nothing, Text};
+use yew::services::fetch::{FetchService, FetchTask, Request, Response};
+use yew::Callback;
+
+use super::async_store::{AsyncStore, Pending};
+use super::error::{DbError, DbResult};
+use super::query::{PersonsPage, PersonsQuery, SortColumn};
+use super::store::LoggedIn;
+
+/// Query parameter of the page holding the base URL of the server, like
+/// `?api=http://localhost:7878`, an empty value means the server of the page
+const API_PARAMETER: &str = "api";
+
+/// Base URL of the server given to the page, `None` to keep the data in
+/// the browser
+pub fn base_url() -> Option<String> {
+    let search = web_sys::window()?.location().search().ok()?;
+
+    api_parameter(&search)
+}
+
+/// Value of the `api` parameter in the query string of the page, without
+/// a trailing slash
+fn api_parameter(search: &str) -> Option<String> {
+    search
+        .trim_start_matches('?')
+        .split('&')
+        .find_map(|pair| match pair.split_once('=') {
+            Some((name, value)) if name == API_PARAMETER => Some(value.to_string()),
+            None if pair == API_PARAMETER => Some(String::default()),
+            _ => None,
+        })
+        .and_then(|url| decode(&url))
+        .map(|url| url.trim_end_matches('/').to_string())
+}
+
+/// Body of the responses to failed requests
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: String,
+    /// The error of the `Database` of the server, if it failed there
+    cause: Option<DbError>,
+}
+
+#[derive(Deserialize)]
+struct LoginBody {
+    token: String,
+    user: AuthenticatedUser,
+}
+
+#[derive(Deserialize)]
+struct InsertedBody {
+    id: u32,
+}
+
+/// Talks to the REST API of the `server` crate, the token of the logged in
+/// user is sent along with every request
+#[derive(Debug)]
+pub struct HttpStore {
+    base_url: String,
+    token: Rc<RefCell<Option<String>>>,
+    /// Logging out on the server, nobody waits for it
+    log_out_task: RefCell<Option<FetchTask>>,
+}
+
+impl HttpStore {
+    pub fn new(base_url: String) -> Self {
+        Self {
+            base_url,
+            token: Rc::default(),
+            log_out_task: RefCell::default(),
+        }
+    }
+
+    /// Sends a request whose answer is handed to `callback` when `active`,
+    /// `version` is sent as `If-Match` to change a person only if it still
+    /// has that version
+    fn fetch<T, B>(
+        &self,
+        method: &str,
+        path: &str,
+        body: B,
+        version: Option<u32>,
+        active: &Rc<Cell<bool>>,
+        callback: Callback<DbResult<T>>,
+    ) -> Option<FetchTask>
+    where
+        T: DeserializeOwned + 'static,
+        B: Into<Text>,
+    {
+        let mut builder = Request::builder()
+            .method(method)
+            .uri(format!("{}{}", self.base_url, path))
+            .header("Content-Type", "application/json");
+
+        if let Some(token) = &*self.token.borrow() {
+            builder = builder.header("Authorization", format!("Bearer {}", token));
+        }
+
+        if let Some(version) = version {
+            builder = builder.header("If-Match", format!("\"{}\"", version));
+        }
+
+        let request = match builder.body(body) {
+            Ok(request) => request,
+            Err(err) => {
+                callback.emit(Err(DbError::Storage(err.to_string())));
+                return None;
+            }
+        };
+        let active = active.clone();
+        let on_response = callback.clone();
+        let on_response = Callback::from(move |response: Response<Text>| {
+            if active.get() {
+                on_response.emit(read_response(response));
+            }
+        });
+
+        match FetchService::fetch(request, on_response) {
+            Ok(task) => Some(task),
+            Err(err) => {
+                callback.emit(Err(DbError::Storage(err.to_string())));
+                None
+            }
+        }
+    }
+
+    /// Sends a single request
+    fn send<T, B>(
+        &self,
+        method: &str,
+        path: &str,
+        body: B,
+        callback: Callback<DbResult<T>>,
+    ) -> Pending
+    where
+        T: DeserializeOwned + 'static,
+        B: Into<Text>,
+    {
+        let active = Rc::new(Cell::new(true));
+        let task = self.fetch(method, path, body, None, &active, callback);
+
+        Pending::new(task.into_iter().collect(), active)
+    }
+}
+
+impl AsyncStore for HttpStore {
+    fn is_remote(&self) -> bool {
+        true
+    }
+
+    fn log_in(
+        &self,
+        username: &str,
+        password: &str,
+        callback: Callback<DbResult<LoggedIn>>,
+    ) -> Pending {
+        let token = self.token.clone();
+        let body = serde_json::json!({ "username": username, "password": password });
+
+        self.send(
+            "POST",
+            "/login",
+            Json(&body),
+            Callback::from(move |result: DbResult<LoginBody>| {
+                callback.emit(result.map(|body| {
+                    *token.borrow_mut() = Some(body.token.clone());
+
+                    LoggedIn {
+                        user: body.user,
+                        token: body.token,
+                    }
+                }))
+            }),
+        )
+    }
+
+    fn resume(
+        &self,
+        _username: &str,
+        token: &str,
+        callback: Callback<DbResult<AuthenticatedUser>>,
+    ) -> Pending {
+        *self.token.borrow_mut() = Some(token.to_string());
+
+        self.send("GET", "/session", Nothing, callback)
+    }
+
+    fn log_out(&self) {
+        if self.token.borrow().is_some() {
+            let active = Rc::new(Cell::new(true));
+            let task =
+                self.fetch::<(), _>("POST", "/logout", Nothing, None, &active, Callback::noop());
+
+            *self.log_out_task.borrow_mut() = task;
+        }
+
+        *self.token.borrow_mut() = None;
+    }
+
+    fn query_persons(
+        &self,
+        query: &PersonsQuery,
+        callback: Callback<DbResult<PersonsPage>>,
+    ) -> Pending {
+        self.send("GET", &persons_path(query), Nothing, callback)
+    }
+
+    fn get_person(&self, id: u32, callback: Callback<DbResult<Person>>) -> Pending {
+        self.send("GET", &format!("/persons/{}", id), Nothing, callback)
+    }
+
+    fn insert(&self, person: Person, callback: Callback<DbResult<u32>>) -> Pending {
+        self.send(
+            "POST",
+            "/persons",
+            Json(&person),
+            Callback::from(move |result: DbResult<InsertedBody>| {
+                callback.emit(result.map(|body| body.id))
+            }),
+        )
+    }
+
+    fn update(&self, person: Person, callback: Callback<DbResult<()>>) -> Pending {
+        let path = format!("/persons/{}", person.id);
+        let active = Rc::new(Cell::new(true));
+        let task = self.fetch(
+            "PUT",
+            &path,
+            Json(&person),
+            Some(person.version),
+            &active,
+            Callback::from(move |result: DbResult<Person>| callback.emit(result.map(|_| ()))),
+        );
+
+        Pending::new(task.into_iter().collect(), active)
+    }
+
+    fn delete(&self, persons: Vec<(u32, u32)>, callback: Callback<Vec<DbResult<()>>>) -> Pending {
+        let active = Rc::new(Cell::new(true));
+        let count = persons.len();
+        let results: Rc<RefCell<Vec<Option<DbResult<()>>>>> =
+            Rc::new(RefCell::new(vec![None; count]));
+
+        if count == 0 {
+            callback.emit(Vec::new());
+        }
+
+        // One request per person, answered together once they all finished
+        let tasks = persons
+            .iter()
+            .enumerate()
+            .filter_map(|(index, (id, version))| {
+                let results = results.clone();
+                let callback = callback.clone();
+                let on_result = Callback::from(move |result: DbResult<()>| {
+                    let mut results = results.borrow_mut();
+
+                    results[index] = Some(result);
+
+                    if results.iter().all(Option::is_some) {
+                        callback.emit(results.drain(..).flatten().collect());
+                    }
+                });
+
+                self.fetch(
+                    "DELETE",
+                    &format!("/persons/{}", id),
+                    Nothing,
+                    Some(*version),
+                    &active,
+                    on_result,
+                )
+            })
+            .collect();
+
+        Pending::new(tasks, active)
+    }
+}
+
+/// Path of `GET /persons` with the query string asking for `query`
+fn persons_path(query: &PersonsQuery) -> String {
+    let mut parameters = vec![
+        format!("text={}", encode(&query.text)),
+        format!("match_case={}", query.match_case),
+        format!("fuzzy={}", query.fuzzy),
+        format!("offset={}", query.offset),
+        format!("limit={}", query.limit),
+    ];
+
+    if let Some(sort) = query.sort {
+        let column = match sort.column {
+            SortColumn::Id => "id",
+            SortColumn::Name => "name",
+        };
+        let order = if sort.ascending { "" } else { "-" };
+
+        parameters.push(format!("sort={}{}", order, column));
+    }
+
+    format!("/persons?{}", parameters.join("&"))
+}
+
+/// The value of a successful response, or the error the server answered
+fn read_response<T: DeserializeOwned>(response: Response<Text>) -> DbResult<T> {
+    let status = response.status();
+    let body = response
+        .into_body()
+        .map_err(|_| DbError::Storage("Unable to reach the server".to_string()))?;
+
+    if status.is_success() {
+        // Empty bodies, like the ones of deletes, stand for `()`
+        let body = if body.is_empty() { "null" } else { &body };
+
+        return serde_json::from_str(body)
+            .map_err(|err| DbError::Storage(format!("Invalid answer of the server: {}", err)));
+    }
+
+    match serde_json::from_str::<ErrorBody>(&body) {
+        Ok(ErrorBody {
+            cause: Some(cause), ..
+        }) => Err(cause),
+        Ok(ErrorBody { error, .. }) if status.as_u16() == 401 => {
+            Err(DbError::Authentication(error))
+        }
+        Ok(ErrorBody { error, .. }) => Err(DbError::Storage(error)),
+        Err(_) => Err(DbError::Storage(format!("The server answered {}", status))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::PersonsSort;
+
+    fn response(status: u16, body: &str) -> Response<Text> {
+        Response::builder()
+            .status(status)
+            .body(Ok(body.to_string()))
+            .unwrap()
+    }
+
+    #[test]
+    fn api_parameter_names_the_server() {
+        let api = api_parameter;
+
+        assert_eq!(
+            api("?api=http%3A%2F%2Flocalhost%3A8080%2F").as_deref(),
+            Some("http://localhost:8080")
+        );
+        assert_eq!(
+            api("?page=2&api=http://localhost:8080/api/").as_deref(),
+            Some("http://localhost:8080/api")
+        );
+        assert_eq!(api("?api").as_deref(), Some(""));
+        assert_eq!(api("?api=").as_deref(), Some(""));
+        assert_eq!(api("?apis=http://localhost:8080"), None);
+        assert_eq!(api("?api=%zz"), None);
+        assert_eq!(api(""), None);
+    }
+
+    #[test]
+    fn persons_path_escapes_the_text_and_sorts() {
+        let query = PersonsQuery {
+            text: "Ana María & co".to_string(),
+            sort: Some(PersonsSort {
+                column: SortColumn::Name,
+                ascending: false,
+            }),
+            offset: 20,
+            limit: 10,
+            ..PersonsQuery::default()
+        };
+
+        assert_eq!(
+            persons_path(&query),
+            "/persons?text=Ana%20Mar%C3%ADa%20%26%20co&match_case=false&fuzzy=false\
+             &offset=20&limit=10&sort=-name"
+        );
+    }
+
+    #[test]
+    fn read_response_reads_successful_answers() {
+        let person: DbResult<Person> = read_response(response(200, r#"{"id":2,"name":"June"}"#));
+        assert_eq!(person.map(|p| p.name), Ok("June".to_string()));
+
+        let deleted: DbResult<()> = read_response(response(204, ""));
+        assert_eq!(deleted, Ok(()));
+
+        let invalid: DbResult<Person> = read_response(response(200, "<html>"));
+        assert!(matches!(invalid, Err(DbError::Storage(_))));
+    }
+
+    #[test]
+    fn read_response_maps_errors_to_db_errors() {
+        let stored = Person::new(2, "June");
+        let body = serde_json::json!({
+            "error": "Conflict",
+            "cause": DbError::Conflict(Box::new(stored.clone())),
+        });
+        let conflict: DbResult<()> = read_response(response(409, &body.to_string()));
+        assert_eq!(conflict, Err(DbError::Conflict(Box::new(stored))));
+
+        let body = r#"{"error": "Log in to use the API", "cause": null}"#;
+        let unauthorized: DbResult<()> = read_response(response(401, body));
+        assert_eq!(
+            unauthorized,
+            Err(DbError::Authentication("Log in to use the API".to_string()))
+        );
+
+        let body = r#"{"error": "Not found", "cause": null}"#;
+        let not_found: DbResult<()> = read_response(response(404, body));
+        assert_eq!(not_found, Err(DbError::Storage("Not found".to_string())));
+
+        let bad_gateway: DbResult<()> = read_response(response(502, "Bad Gateway"));
+        assert!(matches!(bad_gateway, Err(DbError::Storage(_))));
+    }
+
+    #[test]
+    fn read_response_reports_unreachable_servers() {
+        // Fetch answers failed requests with an error instead of a body
+        let response = Response::builder()
+            .status(500)
+            .body(Nothing.into())
+            .unwrap();
+        let result: DbResult<()> = read_response(response);
+
+        assert_eq!(
+            result,
+            Err(DbError::Storage("Unable to reach the server".to_string()))
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{IdbDatabase, IdbOpenDbRequest, IdbRequest, IdbTransactionMode};
+
+use super::error::{DbError, DbResult};
+use super::memory::Database;
+use super::persistent::{Backend, PersistentStore};
+
+const DATABASE_NAME: &str = "yew-app-samples.auth";
+const DATABASE_VERSION: u32 = 1;
+const OBJECT_STORE: &str = "snapshots";
+const SNAPSHOT_KEY: &str = "database";
+
+type Handle = Weak<RefCell<PersistentStore<IndexedDb>>>;
+
+/// Keeps the `Database` serialized as JSON in an IndexedDB object store.
+///
+/// IndexedDB is asynchronous, so the store starts with the seed data and
+/// swaps in the stored snapshot as soon as it is read. Nothing is saved until
+/// then, so the seed data never overwrites the stored one: the app waits for
+/// `is_loaded` before restoring the session or showing any page. A snapshot
+/// that can't be read is reported and never written over.
+///
+/// For the same reason the snapshot is only read again once another tab says
+/// it saved one, not before every change: tabs changing the data at the very
+/// same time may still hand out the same id, the last write winning.
+#[derive(Debug, Default)]
+pub struct IndexedDb {
+    connection: Option<IdbDatabase>,
+    /// Whether the first snapshot was read, or failed to
+    loaded: bool,
+    /// Why the stored snapshot couldn't be read
+    unreadable: Option<String>,
+}
+
+impl IndexedDb {
+    pub fn open() -> Rc<RefCell<PersistentStore<IndexedDb>>> {
+        let store = PersistentStore::new(Database::new(), IndexedDb::default()).share();
+
+        if let Err(err) = IndexedDb::connect(Rc::downgrade(&store)) {
+            IndexedDb::failed(&Rc::downgrade(&store), "Unable to open IndexedDB", err);
+        }
+
+        store
+    }
+
+    fn connect(handle: Handle) -> Result<(), JsValue> {
+        let factory = web_sys::window()
+            .ok_or("no window available")?
+            .indexed_db()?
+            .ok_or("IndexedDB is not supported")?;
+        let request = factory.open_with_u32(DATABASE_NAME, DATABASE_VERSION)?;
+
+        let on_upgrade_needed = Closure::once(move |event: web_sys::Event| {
+            let request: IdbOpenDbRequest = event.target().unwrap().unchecked_into();
+            let connection: IdbDatabase = request.result().unwrap().unchecked_into();
+
+            if !connection.object_store_names().contains(OBJECT_STORE) {
+                connection.create_object_store(OBJECT_STORE).unwrap();
+            }
+        });
+
+        let error_handle = handle.clone();
+        let on_error = Closure::once(move |event: web_sys::Event| {
+            IndexedDb::failed(&error_handle, "Unable to open IndexedDB", event.into());
+        });
+
+        let on_success = Closure::once(move |event: web_sys::Event| {
+            let request: IdbOpenDbRequest = event.target().unwrap().unchecked_into();
+            let connection: IdbDatabase = request.result().unwrap().unchecked_into();
+
+            IndexedDb::on_connected(handle, connection);
+        });
+
+        request.set_onupgradeneeded(Some(on_upgrade_needed.as_ref().unchecked_ref()));
+        request.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+        request.set_onsuccess(Some(on_success.as_ref().unchecked_ref()));
+        on_upgrade_needed.forget();
+        on_error.forget();
+        on_success.forget();
+
+        Ok(())
+    }
+
+    fn on_connected(handle: Handle, connection: IdbDatabase) {
+        let store = match handle.upgrade() {
+            Some(store) => store,
+            None => return,
+        };
+        let restoring = {
+            let backend = &mut store.borrow_mut().backend;
+
+            backend.connection = Some(connection);
+            backend.restore(handle.clone())
+        };
+
+        if let Err(err) = restoring {
+            IndexedDb::failed(&handle, "Unable to read from IndexedDB", err);
+        }
+    }
+
+    /// Gives up reading the snapshot, it is taken as unreadable so nothing
+    /// is saved over it
+    fn failed(handle: &Handle, reason: &str, err: JsValue) {
+        web_sys::console::error_2(&reason.into(), &err);
+
+        let store = match handle.upgrade() {
+            Some(store) => store,
+            None => return,
+        };
+
+        {
+            let backend = &mut store.borrow_mut().backend;
+
+            backend.loaded = true;
+            backend.unreadable = Some(reason.to_string());
+        }
+
+        PersistentStore::notify(&store);
+    }
+
+    fn restore(&self, handle: Handle) -> Result<(), JsValue> {
+        let connection = self
+            .connection
+            .as_ref()
+            .ok_or("IndexedDB is not connected")?;
+        let request = connection
+            .transaction_with_str_and_mode(OBJECT_STORE, IdbTransactionMode::Readonly)?
+            .object_store(OBJECT_STORE)?
+            .get(&JsValue::from_str(SNAPSHOT_KEY))?;
+
+        let error_handle = handle.clone();
+        let on_error = Closure::once(move |event: web_sys::Event| {
+            IndexedDb::failed(&error_handle, "Unable to read from IndexedDB", event.into());
+        });
+
+        let on_success = Closure::once(move |event: web_sys::Event| {
+            let request: IdbRequest = event.target().unwrap().unchecked_into();
+            let snapshot = request.result().ok().and_then(|value| value.as_string());
+            let database = snapshot.map(|json| serde_json::from_str::<Database>(&json));
+            let store = match handle.upgrade() {
+                Some(store) => store,
+                None => return,
+            };
+            let restored = {
+                let backend = &mut store.borrow_mut().backend;
+
+                backend.loaded = true;
+
+                match database {
+                    // Another tab may have discarded the snapshot this one
+                    // couldn't read
+                    Some(Ok(database)) => {
+                        backend.unreadable = None;
+                        Some(database)
+                    }
+                    Some(Err(err)) => {
+                        if backend.unreadable.is_none() {
+                            web_sys::console::error_1(
+                                &format!("Unable to read the data in IndexedDB: {}", err).into(),
+                            );
+                        }
+
+                        backend.unreadable = Some(err.to_string());
+                        None
+                    }
+                    None => None,
+                }
+            };
+
+            // The app waits for the first read, whatever it found
+            match restored {
+                Some(database) => PersistentStore::synced(&store, database),
+                None => PersistentStore::notify(&store),
+            }
+        });
+
+        request.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+        request.set_onsuccess(Some(on_success.as_ref().unchecked_ref()));
+        on_error.forget();
+        on_success.forget();
+
+        Ok(())
+    }
+
+    fn write(connection: &IdbDatabase, database: &Database) -> Result<(), JsValue> {
+        let snapshot =
+            serde_json::to_string(database).map_err(|err| JsValue::from_str(&err.to_string()))?;
+
+        connection
+            .transaction_with_str_and_mode(OBJECT_STORE, IdbTransactionMode::Readwrite)?
+            .object_store(OBJECT_STORE)?
+            .put_with_key(
+                &JsValue::from_str(&snapshot),
+                &JsValue::from_str(SNAPSHOT_KEY),
+            )?;
+
+        Ok(())
+    }
+}
+
+impl Backend for IndexedDb {
+    fn save(&mut self, database: &Database) -> DbResult<()> {
+        if !self.loaded {
+            return Err(DbError::Storage(
+                "The data is still being read from IndexedDB".to_string(),
+            ));
+        }
+
+        if let Some(err) = &self.unreadable {
+            return Err(DbError::Storage(format!(
+                "The data in IndexedDB can't be read, it is kept untouched ({})",
+                err
+            )));
+        }
+
+        let connection = self
+            .connection
+            .as_ref()
+            .ok_or_else(|| DbError::Storage("IndexedDB is not available".to_string()))?;
+
+        IndexedDb::write(connection, database).map_err(|err| {
+            DbError::Storage(
+                err.as_string()
+                    .unwrap_or_else(|| "Unable to write to IndexedDB".to_string()),
+            )
+        })
+    }
+
+    fn load(&mut self) -> Option<Database> {
+        None
+    }
+
+    fn is_loaded(&self) -> bool {
+        self.loaded
+    }
+
+    fn unreadable(&self) -> Option<String> {
+        self.unreadable.clone()
+    }
+
+    fn discard_unreadable(&mut self) {
+        self.unreadable = None;
+    }
+
+    fn reload(store: &Rc<RefCell<PersistentStore<Self>>>) {
+        let backend = &store.borrow().backend;
+
+        // The first snapshot read is still to come, or IndexedDB can't be
+        // opened at all
+        if !backend.loaded || backend.connection.is_none() {
+            return;
+        }
+
+        if let Err(err) = backend.restore(Rc::downgrade(store)) {
+            web_sys::console::error_2(&"Unable to read from IndexedDB".into(), &err);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use web_sys::Storage;
+
+use super::error::{DbError, DbResult};
+use super::memory::Database;
+use super::persistent::{Backend, PersistentStore};
+
+/// Key used to keep the serialized `Database` in the browser's `localStorage`
+const STORAGE_KEY: &str = "yew-app-samples.auth.database";
+
+/// Keeps the `Database` serialized as JSON in `localStorage`
+#[derive(Debug)]
+pub struct LocalStorage {
+    storage: Option<Storage>,
+    /// Why the stored value couldn't be read, it is never overwritten then
+    unreadable: Option<String>,
+    /// Stored value this tab last read or wrote, a different one was saved
+    /// by another tab
+    seen: Option<String>,
+}
+
+impl LocalStorage {
+    /// Restores the `Database` from `localStorage`, starting with the seed
+    /// data when nothing is stored yet. A stored value that can't be read
+    /// is reported and kept as it is, nothing is saved over it
+    pub fn open() -> PersistentStore<LocalStorage> {
+        let storage = web_sys::window().and_then(|window| window.local_storage().ok().flatten());
+        let seen = storage
+            .as_ref()
+            .and_then(|storage| storage.get_item(STORAGE_KEY).ok().flatten());
+        let stored = seen
+            .as_ref()
+            .map(|json| serde_json::from_str::<Database>(json));
+        let (database, unreadable) = match stored {
+            Some(Ok(database)) => (database, None),
+            Some(Err(err)) => {
+                web_sys::console::error_1(
+                    &format!("Unable to read the data in localStorage: {}", err).into(),
+                );
+
+                (Database::new(), Some(err.to_string()))
+            }
+            None => (Database::new(), None),
+        };
+
+        PersistentStore::new(
+            database,
+            LocalStorage {
+                storage,
+                unreadable,
+                seen,
+            },
+        )
+    }
+}
+
+impl Backend for LocalStorage {
+    fn save(&mut self, database: &Database) -> DbResult<()> {
+        let storage = self
+            .storage
+            .as_ref()
+            .ok_or_else(|| DbError::Storage("localStorage is not available".to_string()))?;
+
+        if let Some(err) = &self.unreadable {
+            return Err(DbError::Storage(format!(
+                "The data in localStorage can't be read, it is kept untouched ({})",
+                err
+            )));
+        }
+
+        let json =
+            serde_json::to_string(database).map_err(|err| DbError::Storage(err.to_string()))?;
+
+        storage
+            .set_item(STORAGE_KEY, &json)
+            .map_err(|_| DbError::Storage("Unable to write to localStorage".to_string()))?;
+        self.seen = Some(json);
+
+        Ok(())
+    }
+
+    fn load(&mut self) -> Option<Database> {
+        let json = self
+            .storage
+            .as_ref()?
+            .get_item(STORAGE_KEY)
+            .ok()
+            .flatten()?;
+
+        if self.seen.as_ref() == Some(&json) {
+            return None;
+        }
+
+        match serde_json::from_str(&json) {
+            // Another tab may have discarded the value this one couldn't read
+            Ok(database) => {
+                self.unreadable = None;
+                self.seen = Some(json);
+                Some(database)
+            }
+            Err(_) if self.unreadable.is_some() => None,
+            Err(err) => {
+                web_sys::console::error_1(
+                    &format!("Unable to sync with another tab: {}", err).into(),
+                );
+                None
+            }
+        }
+    }
+
+    fn unreadable(&self) -> Option<String> {
+        self.unreadable.clone()
+    }
+
+    fn discard_unreadable(&mut self) {
+        self.unreadable = None;
+    }
+}
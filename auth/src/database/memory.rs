@@ -0,0 +1,1439 @@
+use crate::person::Person;
+use crate::search;
+use crate::session::SESSION_LIFETIME;
+use crate::user::{AuthenticatedUser, DbPrivilege, PasswordHash, Role, User};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use subtle::ConstantTimeEq;
+use yew::Callback;
+
+use super::audit::{AuditEntry, AuditOperation, AuditQuery};
+use super::backup::{Backup, BackupDiff, RestoreMode, BACKUP_VERSION};
+use super::error::{DbError, DbResult};
+use super::history::{self, History, PersonChange};
+use super::query::{PersonsPage, PersonsQuery, ScoredPerson};
+use super::store::{
+    AuditStore, AuthStore, BackupStore, HistoryStore, LoggedIn, PersonStore, RecoveryStore,
+    SyncStore, TrashStore, UserStore,
+};
+use super::trash::{DeletedPerson, DEFAULT_RETENTION_DAYS};
+
+/// In-memory store, every change is lost when the page is reloaded.
+///
+/// Persistent backends wrap a `Database` and write it out after each
+/// mutation, see `PersistentStore`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "Snapshot")]
+pub struct Database {
+    persons: Vec<Person>,
+    /// Deleted persons, they are purged after `retention_days`
+    trash: Vec<DeletedPerson>,
+    retention_days: u32,
+    users: Vec<User>,
+    /// Id handed to the next inserted person, never reused after a delete
+    next_id: u32,
+    /// Every change made to the persons, oldest first
+    audit: Vec<AuditEntry>,
+    /// Sessions started by logging in that didn't end yet
+    sessions: Vec<StoredSession>,
+    /// Username of the user acting on the data, it is not persisted
+    #[serde(skip_serializing)]
+    actor: Option<String>,
+    /// Hash of the token of the session of the acting user, it is not
+    /// persisted
+    #[serde(skip_serializing)]
+    session: Option<String>,
+    /// Changes of the acting user that can be undone, it is not persisted
+    #[serde(skip_serializing)]
+    history: History,
+}
+
+/// Unchecked shape of a serialized `Database`, validated by `TryFrom`
+#[derive(Deserialize)]
+struct Snapshot {
+    persons: Vec<Person>,
+    #[serde(default)]
+    trash: Vec<DeletedPerson>,
+    #[serde(default = "default_retention_days")]
+    retention_days: u32,
+    users: Vec<User>,
+    #[serde(default)]
+    next_id: u32,
+    #[serde(default)]
+    audit: Vec<AuditEntry>,
+    #[serde(default)]
+    sessions: Vec<StoredSession>,
+}
+
+/// A session started by `log_in`, only the hash of its token is kept so
+/// the stored data is not enough to resume it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct StoredSession {
+    username: String,
+    token_hash: String,
+    expires_at: DateTime<Utc>,
+}
+
+/// Number of random bytes of a session token
+const TOKEN_LENGTH: usize = 32;
+
+/// Hex encoded SHA-256 hash of a session token, tokens are random enough
+/// not to need a salt
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+impl Snapshot {
+    /// Rejects invalid persons, the data stored by older versions is loaded
+    /// with them and repaired as it is edited
+    fn check(&self) -> DbResult<()> {
+        self.persons.iter().try_for_each(Database::validate_person)
+    }
+}
+
+fn default_retention_days() -> u32 {
+    DEFAULT_RETENTION_DAYS
+}
+
+/// Username the audit log shows for persons purged by the retention
+const RETENTION_USERNAME: &str = "(retention)";
+
+impl Database {
+    pub fn new() -> Self {
+        Self {
+            persons: vec![
+                Person::new(1, "Esteban"),
+                Person::new(2, "June"),
+                Person::new(3, "Carlos"),
+                Person::new(4, "Ana"),
+            ],
+            trash: Vec::new(),
+            retention_days: DEFAULT_RETENTION_DAYS,
+            users: vec![
+                User::new(
+                    "root",
+                    PasswordHash::from_hex(
+                        "5d0b6e2f8a41c3973e1ab0c4f6d28e15",
+                        "f98a29e27036840a1f7128eb4f8ca2eb091bb8a7235a33e4506164b8e22fefac",
+                        100_000,
+                    ),
+                    vec![Role::Administrator],
+                ),
+                User::new(
+                    "john",
+                    PasswordHash::from_hex(
+                        "a73c19e4b2d05f6812ce9b3a4d70f6e1",
+                        "d4669ab805ce082b03614f6bf49cc95797dd55a5f290cea325ab7311cb313652",
+                        100_000,
+                    ),
+                    vec![Role::Viewer],
+                ),
+            ],
+            next_id: 5,
+            audit: Vec::new(),
+            sessions: Vec::new(),
+            actor: None,
+            session: None,
+            history: History::default(),
+        }
+    }
+}
+
+impl Database {
+    /// Takes the data of `other`, still acting as the same user
+    #[cfg(any(feature = "local-storage", feature = "indexed-db"))]
+    pub fn replace(&mut self, other: Database) {
+        let actor = self.actor.take();
+        let session = self.session.take();
+
+        *self = other;
+        self.actor = actor;
+        self.session = session;
+    }
+
+    /// Username of the session `token` was handed out for, as long as it
+    /// didn't expire, for clients that only keep the token
+    pub fn session_username(&self, token: &str) -> Option<String> {
+        let token_hash = hash_token(token);
+        let now = Utc::now();
+
+        self.sessions
+            .iter()
+            .find(|session| session.token_hash == token_hash && session.expires_at > now)
+            .map(|session| session.username.clone())
+    }
+
+    /// Stops acting as the logged in user, without ending the session
+    pub fn release(&mut self) {
+        self.actor = None;
+        self.session = None;
+        self.history.clear();
+    }
+
+    /// Whether the acting user is enabled and has `privilege`
+    fn can(&self, privilege: DbPrivilege) -> bool {
+        self.actor.as_ref().is_some_and(|actor| {
+            self.users
+                .iter()
+                .any(|u| u.username == *actor && !u.disabled && u.has_privilege(privilege))
+        })
+    }
+
+    fn authorize(&self, privilege: DbPrivilege) -> DbResult<()> {
+        if self.can(privilege) {
+            Ok(())
+        } else {
+            Err(DbError::PermissionDenied(privilege.action().to_string()))
+        }
+    }
+
+    /// Adds `change` to the audit log on behalf of the acting user
+    fn audit(&mut self, change: &PersonChange) {
+        let username = self.actor.clone().unwrap_or_default();
+
+        self.audit_as(username, change);
+    }
+
+    fn audit_as(&mut self, username: String, change: &PersonChange) {
+        self.audit.push(AuditEntry {
+            timestamp: Utc::now(),
+            username,
+            operation: change.operation,
+            person_id: change.id,
+            before: change.before.clone(),
+            after: change.after.clone(),
+        });
+    }
+
+    /// Audits `change` and makes it undoable
+    fn record(&mut self, change: PersonChange) {
+        self.audit(&change);
+        self.history.record(change);
+    }
+
+    /// Brings a person to the `after` state of `change`, as part of undoing
+    /// or redoing it, persons stay ordered by id
+    fn apply(&mut self, mut change: PersonChange) {
+        let index = self.persons.iter().position(|p| p.id == change.id);
+
+        // Going back to an old state is still a new version, so edits read
+        // before it conflict with it
+        if let Some(person) = &mut change.after {
+            let stored = index.map_or(0, |index| self.persons[index].version);
+
+            person.version = stored.max(person.version) + 1;
+        }
+
+        match (index, &change.after) {
+            (Some(index), Some(person)) => self.persons[index] = person.clone(),
+            (Some(index), None) => {
+                let person = self.persons.remove(index);
+
+                self.move_to_trash(person);
+            }
+            (None, Some(person)) => {
+                self.trash.retain(|deleted| deleted.person.id != person.id);
+
+                let index = self
+                    .persons
+                    .iter()
+                    .position(|p| p.id > person.id)
+                    .unwrap_or(self.persons.len());
+
+                self.persons.insert(index, person.clone());
+            }
+            (None, None) => {}
+        }
+
+        self.audit(&change);
+    }
+
+    /// Checks the acting user may make every change in `changes`
+    fn authorize_changes(&self, changes: &[PersonChange]) -> DbResult<()> {
+        changes.iter().try_for_each(|change| {
+            self.authorize(match change.operation {
+                AuditOperation::Insert | AuditOperation::Restore => DbPrivilege::CanCreate,
+                AuditOperation::Update => DbPrivilege::CanUpdate,
+                AuditOperation::Delete | AuditOperation::Purge => DbPrivilege::CanDelete,
+            })
+        })
+    }
+
+    fn move_to_trash(&mut self, person: Person) {
+        self.trash.push(DeletedPerson {
+            person,
+            deleted_at: Utc::now(),
+            deleted_by: self.actor.clone().unwrap_or_default(),
+        });
+    }
+
+    /// Removes `deleted` for good, it can't be undone anymore
+    fn purge_as(&mut self, username: String, deleted: DeletedPerson) {
+        let id = deleted.person.id;
+
+        self.audit_as(
+            username,
+            &PersonChange {
+                operation: AuditOperation::Purge,
+                id,
+                before: Some(deleted.person),
+                after: None,
+            },
+        );
+        self.history.forget(id);
+    }
+
+    /// Purges the persons deleted longer than the retention period ago
+    fn purge_expired(&mut self, now: DateTime<Utc>) {
+        let limit = now - Duration::days(i64::from(self.retention_days));
+        let (expired, kept) = std::mem::take(&mut self.trash)
+            .into_iter()
+            .partition(|deleted| deleted.deleted_at < limit);
+
+        self.trash = kept;
+
+        for deleted in expired {
+            self.purge_as(RETENTION_USERNAME.to_string(), deleted);
+        }
+    }
+
+    /// The data restoring `backup` would leave, checked like a stored
+    /// snapshot, the acting user must still be able to manage users in it
+    fn restored(&self, backup: &Backup, mode: RestoreMode) -> DbResult<Database> {
+        self.authorize(DbPrivilege::CanManageUsers)?;
+
+        let snapshot = match mode {
+            RestoreMode::Replace => Snapshot {
+                persons: backup.persons.clone(),
+                trash: backup.trash.clone(),
+                retention_days: backup.retention_days,
+                users: backup.users.clone(),
+                next_id: backup.next_id,
+                audit: Vec::new(),
+                sessions: self.sessions.clone(),
+            },
+            RestoreMode::Merge => {
+                let mut persons = self.persons.clone();
+
+                for person in &backup.persons {
+                    match persons.iter_mut().find(|p| p.id == person.id) {
+                        Some(stored) => *stored = person.clone(),
+                        None => persons.push(person.clone()),
+                    }
+                }
+
+                persons.sort_by_key(|p| p.id);
+
+                // Persons of the backup come back from the trash, and the
+                // trash of the backup only adds ids unknown so far
+                let mut trash: Vec<DeletedPerson> = self
+                    .trash
+                    .iter()
+                    .filter(|deleted| !persons.iter().any(|p| p.id == deleted.person.id))
+                    .cloned()
+                    .collect();
+
+                for deleted in &backup.trash {
+                    let id = deleted.person.id;
+
+                    if !persons.iter().any(|p| p.id == id)
+                        && !trash.iter().any(|d| d.person.id == id)
+                    {
+                        trash.push(deleted.clone());
+                    }
+                }
+
+                let mut users = self.users.clone();
+
+                for user in &backup.users {
+                    match users.iter_mut().find(|u| u.username == user.username) {
+                        Some(stored) => *stored = user.clone(),
+                        None => users.push(user.clone()),
+                    }
+                }
+
+                Snapshot {
+                    persons,
+                    trash,
+                    retention_days: self.retention_days,
+                    users,
+                    next_id: self.next_id.max(backup.next_id),
+                    audit: Vec::new(),
+                    sessions: self.sessions.clone(),
+                }
+            }
+        };
+
+        snapshot.check()?;
+
+        let mut database = Database::try_from(snapshot)?;
+
+        // Persons changed by the restore get a new version, so edits read
+        // before it conflict with it, unchanged ones keep theirs
+        for person in &mut database.persons {
+            if let Some(stored) = self.persons.iter().find(|p| p.id == person.id) {
+                let unchanged = Person {
+                    version: stored.version,
+                    ..person.clone()
+                } == *stored;
+
+                person.version = if unchanged {
+                    stored.version
+                } else {
+                    stored.version.max(person.version) + 1
+                };
+            }
+        }
+
+        database.actor = self.actor.clone();
+        database.session = self.session.clone();
+
+        if !database.can(DbPrivilege::CanManageUsers) {
+            return Err(DbError::Validation(
+                "The backup must keep your user enabled and able to manage users".to_string(),
+            ));
+        }
+
+        Ok(database)
+    }
+
+    /// Differences between the current data and `other`
+    fn diff(&self, other: &Database) -> BackupDiff {
+        let find = |persons: &[Person], id| persons.iter().find(|p| p.id == id).cloned();
+        let has_user =
+            |users: &[User], username: &str| users.iter().any(|u| u.username == username);
+
+        BackupDiff {
+            added_persons: other
+                .persons
+                .iter()
+                .filter(|p| find(&self.persons, p.id).is_none())
+                .cloned()
+                .collect(),
+            changed_persons: other
+                .persons
+                .iter()
+                .filter_map(|p| {
+                    find(&self.persons, p.id)
+                        .filter(|stored| stored != p)
+                        .map(|stored| (stored, p.clone()))
+                })
+                .collect(),
+            removed_persons: self
+                .persons
+                .iter()
+                .filter(|p| find(&other.persons, p.id).is_none())
+                .cloned()
+                .collect(),
+            added_users: other
+                .users
+                .iter()
+                .filter(|u| !has_user(&self.users, &u.username))
+                .map(|u| u.username.clone())
+                .collect(),
+            changed_users: other
+                .users
+                .iter()
+                .filter(|u| {
+                    self.users
+                        .iter()
+                        .any(|stored| stored.username == u.username && stored != *u)
+                })
+                .map(|u| u.username.clone())
+                .collect(),
+            removed_users: self
+                .users
+                .iter()
+                .filter(|u| !has_user(&other.users, &u.username))
+                .map(|u| u.username.clone())
+                .collect(),
+        }
+    }
+
+    fn validate_person(person: &Person) -> DbResult<()> {
+        person.validate().map_err(|errors| {
+            let messages: Vec<String> = errors
+                .iter()
+                .map(|(field, message)| format!("{}: {}", field.label(), message))
+                .collect();
+
+            DbError::Validation(messages.join(", "))
+        })
+    }
+
+    /// Whether any field of `person` contains `needle`, which must be
+    /// already normalized with `search::normalize`
+    fn matches(person: &Person, needle: &str, match_case: bool) -> bool {
+        person
+            .searchable_fields()
+            .iter()
+            .any(|field| search::normalize(field, match_case).contains(needle))
+    }
+
+    /// Persons scoring at least `search::FUZZY_THRESHOLD` against `text`,
+    /// best matches first
+    fn rank_persons(&self, text: &str) -> Vec<(&Person, f32)> {
+        let mut ranked: Vec<(&Person, f32)> = self
+            .persons
+            .iter()
+            .map(|person| (person, search::fuzzy_score(&person.name, text)))
+            .filter(|(_, score)| *score >= search::FUZZY_THRESHOLD)
+            .collect();
+
+        ranked.sort_by(|(a, a_score), (b, b_score)| {
+            b_score
+                .partial_cmp(a_score)
+                .unwrap_or(Ordering::Equal)
+                .then(a.id.cmp(&b.id))
+        });
+
+        ranked
+    }
+
+    fn find_user_mut(&mut self, username: &str) -> DbResult<&mut User> {
+        self.users
+            .iter_mut()
+            .find(|u| u.username == username)
+            .ok_or_else(|| DbError::NotFound(format!("User {}", username)))
+    }
+
+    /// Applies `change` to a copy of the users and only keeps it when at
+    /// least one enabled user is still able to manage users, so the
+    /// `Database` can never be locked out of its own administration
+    fn change_users<F>(&mut self, change: F) -> DbResult<()>
+    where
+        F: FnOnce(&mut Vec<User>) -> DbResult<()>,
+    {
+        let mut users = self.users.clone();
+
+        change(&mut users)?;
+
+        let has_admin = users
+            .iter()
+            .any(|u| !u.disabled && u.has_privilege(DbPrivilege::CanManageUsers));
+
+        if !has_admin {
+            return Err(DbError::Validation(
+                "At least one enabled user must be able to manage users".to_string(),
+            ));
+        }
+
+        self.users = users;
+
+        Ok(())
+    }
+}
+
+impl TryFrom<Snapshot> for Database {
+    type Error = DbError;
+
+    /// Rejects snapshots with repeated person ids or usernames, and makes
+    /// sure the id sequence is ahead of every stored id. Persons stored
+    /// before they were validated are kept, they are validated when changed
+    fn try_from(snapshot: Snapshot) -> Result<Self, Self::Error> {
+        let mut ids = HashSet::new();
+        let trashed = snapshot.trash.iter().map(|deleted| &deleted.person);
+
+        for person in snapshot.persons.iter().chain(trashed) {
+            if !ids.insert(person.id) {
+                return Err(DbError::Duplicate(format!("Person with ID {}", person.id)));
+            }
+        }
+
+        let max_id = ids.iter().copied().max().unwrap_or(0);
+        let mut usernames = HashSet::new();
+
+        for user in &snapshot.users {
+            if !usernames.insert(user.username.as_str()) {
+                return Err(DbError::Duplicate(format!("User {}", user.username)));
+            }
+        }
+
+        let mut users = snapshot.users;
+
+        // Snapshots written before user administration existed have nobody
+        // able to manage users, writers are promoted to keep the app usable
+        if !users
+            .iter()
+            .any(|u| u.has_privilege(DbPrivilege::CanManageUsers))
+        {
+            for user in users
+                .iter_mut()
+                .filter(|u| u.has_privilege(DbPrivilege::CanUpdate))
+            {
+                user.roles.push(Role::Administrator);
+            }
+        }
+
+        let mut database = Self {
+            persons: snapshot.persons,
+            trash: snapshot.trash,
+            retention_days: snapshot.retention_days,
+            users,
+            next_id: snapshot.next_id.max(max_id + 1),
+            audit: snapshot.audit,
+            sessions: snapshot.sessions,
+            actor: None,
+            session: None,
+            history: History::default(),
+        };
+
+        database.purge_expired(Utc::now());
+
+        Ok(database)
+    }
+}
+
+impl Default for Database {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AuthStore for Database {
+    fn log_in(&mut self, username: &str, password: &str) -> DbResult<LoggedIn> {
+        // Unknown usernames fail like wrong passwords, and take as long
+        let invalid = || DbError::Authentication("Invalid username or password".to_string());
+        let user = match self.users.iter().find(|u| u.username == username) {
+            Some(user) => user,
+            None => {
+                PasswordHash::verify_unknown(password);
+                return Err(invalid());
+            }
+        };
+
+        if !user.verify_password(password) {
+            return Err(invalid());
+        }
+
+        if user.disabled {
+            return Err(DbError::Authentication("This user is disabled".to_string()));
+        }
+
+        let user = AuthenticatedUser::from(user);
+        let mut bytes = [0u8; TOKEN_LENGTH];
+
+        getrandom::getrandom(&mut bytes).expect("Unable to generate a session token");
+
+        let token = hex::encode(bytes);
+        let now = Utc::now();
+
+        self.sessions.retain(|session| session.expires_at > now);
+        self.sessions.push(StoredSession {
+            username: user.username.clone(),
+            token_hash: hash_token(&token),
+            expires_at: now + SESSION_LIFETIME,
+        });
+        self.actor = Some(user.username.clone());
+        self.session = Some(hash_token(&token));
+        self.history.clear();
+        self.purge_expired(now);
+
+        Ok(LoggedIn { user, token })
+    }
+
+    fn resume(&mut self, username: &str, token: &str) -> DbResult<AuthenticatedUser> {
+        let invalid = || DbError::Authentication("The session is no longer valid".to_string());
+        let token_hash = hash_token(token);
+        let now = Utc::now();
+
+        let started = self.sessions.iter().any(|session| {
+            let same_token: bool = session
+                .token_hash
+                .as_bytes()
+                .ct_eq(token_hash.as_bytes())
+                .into();
+
+            same_token && session.username == username && session.expires_at > now
+        });
+
+        if !started {
+            return Err(invalid());
+        }
+
+        let user = self
+            .users
+            .iter()
+            .find(|u| u.username == username && !u.disabled)
+            .map(AuthenticatedUser::from)
+            .ok_or_else(invalid)?;
+
+        self.actor = Some(user.username.clone());
+        self.session = Some(token_hash);
+        self.history.clear();
+        self.purge_expired(now);
+
+        Ok(user)
+    }
+
+    fn log_out(&mut self) {
+        if let Some(token_hash) = self.session.take() {
+            self.sessions
+                .retain(|session| session.token_hash != token_hash);
+        }
+
+        self.release();
+    }
+}
+
+impl PersonStore for Database {
+    fn get_all_persons(&self) -> Vec<Person> {
+        if !self.can(DbPrivilege::CanRead) {
+            return Vec::new();
+        }
+
+        self.persons.clone()
+    }
+
+    fn get_persons_by_name(&self, partial: &str) -> Vec<Person> {
+        if !self.can(DbPrivilege::CanRead) {
+            return Vec::new();
+        }
+
+        let partial = search::normalize(partial, false);
+
+        self.persons
+            .iter()
+            .filter(|p| search::normalize(&p.name, false).contains(&partial))
+            .cloned()
+            .collect()
+    }
+
+    fn search_persons(&self, text: &str) -> Vec<Person> {
+        if !self.can(DbPrivilege::CanRead) {
+            return Vec::new();
+        }
+
+        let text = search::normalize(text, false);
+
+        self.persons
+            .iter()
+            .filter(|p| Database::matches(p, &text, false))
+            .cloned()
+            .collect()
+    }
+
+    fn fuzzy_search_persons(&self, text: &str) -> Vec<ScoredPerson> {
+        if !self.can(DbPrivilege::CanRead) {
+            return Vec::new();
+        }
+
+        self.rank_persons(text)
+            .into_iter()
+            .map(|(person, score)| ScoredPerson {
+                person: person.clone(),
+                score,
+            })
+            .collect()
+    }
+
+    fn query_persons(&self, query: &PersonsQuery) -> PersonsPage {
+        if !self.can(DbPrivilege::CanRead) {
+            return PersonsPage::default();
+        }
+
+        if query.fuzzy && !query.text.trim().is_empty() {
+            let ranked = self.rank_persons(&query.text);
+            let page: Vec<(&Person, f32)> = ranked
+                .iter()
+                .skip(query.offset)
+                .take(query.limit)
+                .cloned()
+                .collect();
+
+            return PersonsPage {
+                total: ranked.len(),
+                scores: page
+                    .iter()
+                    .map(|(person, score)| (person.id, *score))
+                    .collect(),
+                persons: page.into_iter().map(|(person, _)| person.clone()).collect(),
+            };
+        }
+
+        let text = search::normalize(&query.text, query.match_case);
+        let mut matches: Vec<&Person> = self
+            .persons
+            .iter()
+            .filter(|p| Database::matches(p, &text, query.match_case))
+            .collect();
+
+        if let Some(sort) = query.sort {
+            matches.sort_by(|a, b| sort.compare(a, b));
+        }
+
+        PersonsPage {
+            total: matches.len(),
+            persons: matches
+                .into_iter()
+                .skip(query.offset)
+                .take(query.limit)
+                .cloned()
+                .collect(),
+            scores: HashMap::new(),
+        }
+    }
+
+    fn get_person_by_id(&self, id: u32) -> Option<Person> {
+        if !self.can(DbPrivilege::CanRead) {
+            return None;
+        }
+
+        self.persons.iter().find(|p| p.id == id).cloned()
+    }
+
+    fn delete(&mut self, id: u32, version: Option<u32>) -> DbResult<()> {
+        self.authorize(DbPrivilege::CanDelete)?;
+
+        let person_index = self
+            .persons
+            .iter()
+            .position(|p| p.id == id)
+            .ok_or_else(|| DbError::NotFound(format!("Person with ID {}", id)))?;
+
+        if version.is_some_and(|version| version != self.persons[person_index].version) {
+            return Err(DbError::Conflict(Box::new(
+                self.persons[person_index].clone(),
+            )));
+        }
+
+        let person = self.persons.remove(person_index);
+
+        self.move_to_trash(person.clone());
+        self.record(PersonChange {
+            operation: AuditOperation::Delete,
+            id,
+            before: Some(person),
+            after: None,
+        });
+
+        Ok(())
+    }
+
+    fn insert(&mut self, mut person: Person) -> DbResult<u32> {
+        self.authorize(DbPrivilege::CanCreate)?;
+        Database::validate_person(&person)?;
+
+        let id = self.next_id;
+
+        self.next_id += 1;
+        person.id = id;
+        person.version = 1;
+        self.record(PersonChange {
+            operation: AuditOperation::Insert,
+            id,
+            before: None,
+            after: Some(person.clone()),
+        });
+        self.persons.push(person);
+
+        Ok(id)
+    }
+
+    fn update(&mut self, mut person: Person) -> DbResult<()> {
+        self.authorize(DbPrivilege::CanUpdate)?;
+        Database::validate_person(&person)?;
+
+        let stored = self
+            .persons
+            .iter_mut()
+            .find(|p| p.id == person.id)
+            .ok_or_else(|| DbError::NotFound(format!("Person with ID {}", person.id)))?;
+
+        if stored.version != person.version {
+            return Err(DbError::Conflict(Box::new(stored.clone())));
+        }
+
+        person.version += 1;
+
+        let before = std::mem::replace(stored, person.clone());
+
+        self.record(PersonChange {
+            operation: AuditOperation::Update,
+            id: person.id,
+            before: Some(before),
+            after: Some(person),
+        });
+
+        Ok(())
+    }
+
+    fn export_persons(&self, ids: &[u32]) -> DbResult<Vec<Person>> {
+        self.authorize(DbPrivilege::CanExport)?;
+
+        let persons: HashMap<u32, &Person> = self.persons.iter().map(|p| (p.id, p)).collect();
+
+        Ok(ids
+            .iter()
+            .filter_map(|id| persons.get(id).map(|person| (*person).clone()))
+            .collect())
+    }
+
+    fn import_persons(
+        &mut self,
+        inserts: Vec<Person>,
+        mut updates: Vec<Person>,
+    ) -> DbResult<(usize, usize)> {
+        if !inserts.is_empty() {
+            self.authorize(DbPrivilege::CanCreate)?;
+        }
+        if !updates.is_empty() {
+            self.authorize(DbPrivilege::CanUpdate)?;
+        }
+
+        // Everything is checked up front so no person is stored when the
+        // import can't be completed
+        for person in inserts.iter().chain(&updates) {
+            Database::validate_person(person)?;
+        }
+
+        let mut ids = HashSet::new();
+
+        for person in &mut updates {
+            if !ids.insert(person.id) {
+                return Err(DbError::Validation(format!(
+                    "Person with ID {} is updated more than once",
+                    person.id
+                )));
+            }
+
+            match self.persons.iter().find(|p| p.id == person.id) {
+                // Files carry no versions, the import overwrites
+                Some(stored) => person.version = stored.version,
+                None => return Err(DbError::NotFound(format!("Person with ID {}", person.id))),
+            }
+        }
+
+        let counts = (inserts.len(), updates.len());
+
+        self.history.begin_batch();
+        let result = inserts
+            .into_iter()
+            .try_for_each(|person| self.insert(person).map(|_| ()))
+            .and_then(|_| {
+                updates
+                    .into_iter()
+                    .try_for_each(|person| self.update(person))
+            });
+        self.history.end_batch();
+
+        result.map(|_| counts)
+    }
+}
+
+impl UserStore for Database {
+    fn get_all_users(&self) -> Vec<User> {
+        if !self.can(DbPrivilege::CanManageUsers) {
+            return Vec::new();
+        }
+
+        self.users.clone()
+    }
+
+    fn get_user_by_username(&self, username: &str) -> Option<User> {
+        if !self.can(DbPrivilege::CanManageUsers) {
+            return None;
+        }
+
+        self.users.iter().find(|u| u.username == username).cloned()
+    }
+
+    fn insert_user(
+        &mut self,
+        username: &str,
+        password: PasswordHash,
+        roles: Vec<Role>,
+    ) -> DbResult<()> {
+        self.authorize(DbPrivilege::CanManageUsers)?;
+
+        let username = username.trim();
+
+        if username.is_empty() {
+            return Err(DbError::Validation("Username can't be empty".to_string()));
+        }
+
+        if self.users.iter().any(|u| u.username == username) {
+            return Err(DbError::Duplicate(format!("User {}", username)));
+        }
+
+        self.users.push(User::new(username, password, roles));
+
+        Ok(())
+    }
+
+    fn delete_user(&mut self, username: &str) -> DbResult<()> {
+        self.authorize(DbPrivilege::CanManageUsers)?;
+        self.find_user_mut(username)?;
+        self.change_users(|users| {
+            users.retain(|u| u.username != username);
+
+            Ok(())
+        })
+    }
+
+    fn set_user_disabled(&mut self, username: &str, disabled: bool) -> DbResult<()> {
+        self.authorize(DbPrivilege::CanManageUsers)?;
+        self.find_user_mut(username)?;
+        self.change_users(|users| {
+            for user in users.iter_mut().filter(|u| u.username == username) {
+                user.disabled = disabled;
+            }
+
+            Ok(())
+        })
+    }
+
+    fn reset_password(&mut self, username: &str, password: PasswordHash) -> DbResult<()> {
+        self.authorize(DbPrivilege::CanManageUsers)?;
+        self.find_user_mut(username)?.password = password;
+
+        Ok(())
+    }
+
+    fn grant_role(&mut self, username: &str, role: Role) -> DbResult<()> {
+        self.authorize(DbPrivilege::CanManageUsers)?;
+
+        let user = self.find_user_mut(username)?;
+
+        if !user.has_role(role) {
+            user.roles.push(role);
+        }
+
+        Ok(())
+    }
+
+    fn revoke_role(&mut self, username: &str, role: Role) -> DbResult<()> {
+        self.authorize(DbPrivilege::CanManageUsers)?;
+        self.find_user_mut(username)?;
+        self.change_users(|users| {
+            for user in users.iter_mut().filter(|u| u.username == username) {
+                user.roles.retain(|r| *r != role);
+            }
+
+            Ok(())
+        })
+    }
+}
+
+impl AuditStore for Database {
+    fn query_audit(&self, query: &AuditQuery) -> Vec<AuditEntry> {
+        if !self.can(DbPrivilege::CanAudit) {
+            return Vec::new();
+        }
+
+        self.audit
+            .iter()
+            .rev()
+            .filter(|entry| query.matches(entry))
+            .cloned()
+            .collect()
+    }
+}
+
+impl HistoryStore for Database {
+    fn begin_batch(&mut self) {
+        self.history.begin_batch();
+    }
+
+    fn end_batch(&mut self) {
+        self.history.end_batch();
+    }
+
+    fn can_undo(&self) -> bool {
+        self.history.next_undo().is_some()
+    }
+
+    fn can_redo(&self) -> bool {
+        self.history.next_redo().is_some()
+    }
+
+    fn undo(&mut self) -> DbResult<String> {
+        let changes = self
+            .history
+            .next_undo()
+            .ok_or_else(|| DbError::NotFound("Change to undo".to_string()))?;
+        let inverse: Vec<PersonChange> = changes.iter().rev().map(PersonChange::inverse).collect();
+
+        self.authorize_changes(&inverse)?;
+
+        let changes = self.history.undone().unwrap_or_default();
+
+        for change in inverse {
+            self.apply(change);
+        }
+
+        Ok(history::describe(&changes))
+    }
+
+    fn redo(&mut self) -> DbResult<String> {
+        let changes = self
+            .history
+            .next_redo()
+            .ok_or_else(|| DbError::NotFound("Change to redo".to_string()))?
+            .to_vec();
+
+        self.authorize_changes(&changes)?;
+        self.history.redone();
+
+        for change in changes.iter().cloned() {
+            self.apply(change);
+        }
+
+        Ok(history::describe(&changes))
+    }
+}
+
+impl TrashStore for Database {
+    fn get_trash(&self) -> Vec<DeletedPerson> {
+        if !self.can(DbPrivilege::CanRead) {
+            return Vec::new();
+        }
+
+        let mut trash = self.trash.clone();
+
+        trash.sort_by_key(|deleted| std::cmp::Reverse(deleted.deleted_at));
+
+        trash
+    }
+
+    fn restore(&mut self, id: u32) -> DbResult<()> {
+        self.authorize(DbPrivilege::CanCreate)?;
+
+        let person = self
+            .trash
+            .iter()
+            .find(|deleted| deleted.person.id == id)
+            .map(|deleted| deleted.person.clone())
+            .ok_or_else(|| DbError::NotFound(format!("Deleted person with ID {}", id)))?;
+        let change = PersonChange {
+            operation: AuditOperation::Restore,
+            id,
+            before: None,
+            after: Some(person),
+        };
+
+        self.apply(change.clone());
+        self.history.record(change);
+
+        Ok(())
+    }
+
+    fn purge(&mut self, id: u32) -> DbResult<()> {
+        self.authorize(DbPrivilege::CanDelete)?;
+
+        let index = self
+            .trash
+            .iter()
+            .position(|deleted| deleted.person.id == id)
+            .ok_or_else(|| DbError::NotFound(format!("Deleted person with ID {}", id)))?;
+        let deleted = self.trash.remove(index);
+        let username = self.actor.clone().unwrap_or_default();
+
+        self.purge_as(username, deleted);
+
+        Ok(())
+    }
+
+    fn retention_days(&self) -> u32 {
+        self.retention_days
+    }
+
+    fn set_retention_days(&mut self, days: u32) -> DbResult<()> {
+        self.authorize(DbPrivilege::CanDelete)?;
+
+        if days == 0 {
+            return Err(DbError::Validation(
+                "Deleted persons must be kept at least one day".to_string(),
+            ));
+        }
+
+        self.retention_days = days;
+        self.purge_expired(Utc::now());
+
+        Ok(())
+    }
+}
+
+impl SyncStore for Database {
+    /// A `Database` on its own is not shared, nobody else changes it
+    fn subscribe(&mut self, _callback: Callback<()>) {}
+
+    fn is_loaded(&self) -> bool {
+        true
+    }
+}
+
+impl RecoveryStore for Database {
+    /// A `Database` on its own is never read from anywhere
+    fn unreadable(&self) -> Option<String> {
+        None
+    }
+
+    fn discard_unreadable(&mut self) -> DbResult<()> {
+        Ok(())
+    }
+}
+
+impl BackupStore for Database {
+    fn backup(&self) -> DbResult<Backup> {
+        self.authorize(DbPrivilege::CanManageUsers)?;
+
+        Ok(Backup {
+            version: BACKUP_VERSION,
+            created_at: Utc::now(),
+            created_by: self.actor.clone().unwrap_or_default(),
+            next_id: self.next_id,
+            retention_days: self.retention_days,
+            persons: self.persons.clone(),
+            trash: self.trash.clone(),
+            users: self.users.clone(),
+        })
+    }
+
+    fn preview_restore(&self, backup: &Backup, mode: RestoreMode) -> DbResult<BackupDiff> {
+        let restored = self.restored(backup, mode)?;
+
+        Ok(self.diff(&restored))
+    }
+
+    fn restore_backup(&mut self, backup: &Backup, mode: RestoreMode) -> DbResult<BackupDiff> {
+        let restored = self.restored(backup, mode)?;
+        let diff = self.diff(&restored);
+        let in_trash = |trash: &[DeletedPerson], id| trash.iter().any(|d| d.person.id == id);
+
+        let added = diff.added_persons.iter().map(|person| PersonChange {
+            operation: if in_trash(&self.trash, person.id) {
+                AuditOperation::Restore
+            } else {
+                AuditOperation::Insert
+            },
+            id: person.id,
+            before: None,
+            after: Some(person.clone()),
+        });
+        let changed = diff
+            .changed_persons
+            .iter()
+            .map(|(stored, person)| PersonChange {
+                operation: AuditOperation::Update,
+                id: person.id,
+                before: Some(stored.clone()),
+                after: Some(person.clone()),
+            });
+        let removed = diff.removed_persons.iter().map(|person| PersonChange {
+            operation: if in_trash(&restored.trash, person.id) {
+                AuditOperation::Delete
+            } else {
+                AuditOperation::Purge
+            },
+            id: person.id,
+            before: Some(person.clone()),
+            after: None,
+        });
+        let changes: Vec<PersonChange> = added.chain(changed).chain(removed).collect();
+
+        let Database {
+            persons,
+            trash,
+            retention_days,
+            users,
+            next_id,
+            audit,
+            ..
+        } = restored;
+
+        self.persons = persons;
+        self.trash = trash;
+        self.retention_days = retention_days;
+        self.users = users;
+        self.next_id = next_id;
+        // Persons of the backup purged by the retention while loading it
+        self.audit.extend(audit);
+
+        for change in &changes {
+            self.audit(change);
+        }
+
+        // The history refers to the data before the restore
+        self.history.clear();
+
+        Ok(diff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// JSON of a stored `Database` with the seed users and `persons`
+    fn stored(persons: serde_json::Value) -> String {
+        let mut snapshot = serde_json::to_value(Database::new()).unwrap();
+
+        snapshot["persons"] = persons;
+        snapshot.as_object_mut().unwrap().remove("next_id");
+
+        snapshot.to_string()
+    }
+
+    #[test]
+    fn load_rejects_repeated_ids() {
+        let json = stored(json!([
+            { "id": 1, "name": "Esteban" },
+            { "id": 3, "name": "June" },
+            { "id": 3, "name": "Carlos" },
+        ]));
+        let err = serde_json::from_str::<Database>(&json).unwrap_err();
+
+        assert!(err.to_string().contains("Person with ID 3"), "{}", err);
+    }
+
+    #[test]
+    fn load_moves_the_id_sequence_ahead_of_the_stored_ids() {
+        let json = stored(json!([
+            { "id": 1, "name": "Esteban" },
+            { "id": 7, "name": "June" },
+        ]));
+        let mut database: Database = serde_json::from_str(&json).unwrap();
+
+        database.log_in("root", "root").unwrap();
+        assert_eq!(database.insert(Person::new(0, "Ana")), Ok(8));
+    }
+
+    #[test]
+    fn load_plain_text_passwords() {
+        let json = r#"{
+            "persons": [{ "id": 1, "name": "Esteban" }, { "id": 2, "name": "June" }],
+            "users": [
+                { "username": "root", "password": "root", "privileges": ["CanRead", "CanWrite"] },
+                { "username": "john", "password": "john", "privileges": ["CanRead"] }
+            ]
+        }"#;
+        let mut database: Database = serde_json::from_str(json).unwrap();
+
+        assert_eq!(database.persons.len(), 2);
+        assert!(database.log_in("john", "john").is_ok());
+        assert!(database.log_in("root", "john").is_err());
+        let root = database.log_in("root", "root").unwrap().user;
+        assert!(root.has_privilege(DbPrivilege::CanManageUsers));
+    }
+
+    #[test]
+    fn load_persons_stored_before_validation() {
+        let json = stored(json!([
+            { "id": 1, "name": "" },
+            { "id": 2, "name": "June", "email": "june" },
+        ]));
+        let mut database: Database = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(database.persons.len(), 2);
+
+        database.log_in("root", "root").unwrap();
+
+        let mut person = database.get_person_by_id(1).unwrap();
+        assert!(matches!(
+            database.update(person.clone()),
+            Err(DbError::Validation(_))
+        ));
+
+        person.name = "Esteban".to_string();
+        assert_eq!(database.update(person), Ok(()));
+    }
+
+    #[test]
+    fn log_in_fails_alike_for_unknown_users_and_wrong_passwords() {
+        let mut database = Database::new();
+
+        assert_eq!(
+            database.log_in("nobody", "root").map(|_| ()),
+            database.log_in("root", "wrong").map(|_| ())
+        );
+    }
+
+    #[test]
+    fn resume_needs_the_token_of_the_session() {
+        let mut database = Database::new();
+        let token = database.log_in("root", "root").unwrap().token;
+
+        database.release();
+
+        assert!(matches!(
+            database.resume("john", &token),
+            Err(DbError::Authentication(_))
+        ));
+        assert!(matches!(
+            database.resume("root", "forged"),
+            Err(DbError::Authentication(_))
+        ));
+        assert!(database.get_all_persons().is_empty());
+
+        assert_eq!(database.resume("root", &token).unwrap().username, "root");
+        assert_eq!(database.get_all_persons().len(), 4);
+    }
+
+    #[test]
+    fn sessions_survive_a_reload_but_not_a_log_out() {
+        let mut database = Database::new();
+        let token = database.log_in("root", "root").unwrap().token;
+        let json = serde_json::to_string(&database).unwrap();
+
+        assert!(!json.contains(&token));
+
+        let mut reloaded: Database = serde_json::from_str(&json).unwrap();
+
+        assert!(reloaded.resume("root", &token).is_ok());
+
+        reloaded.log_out();
+
+        assert!(reloaded.resume("root", &token).is_err());
+    }
+
+    #[test]
+    fn import_changes_nothing_when_an_id_repeats() {
+        let mut database = Database::new();
+        database.log_in("root", "root").unwrap();
+
+        let persons = database.get_all_persons();
+        let result = database.import_persons(
+            vec![Person::new(0, "Ana María")],
+            vec![Person::new(2, "Juniper"), Person::new(2, "Junie")],
+        );
+
+        assert!(matches!(result, Err(DbError::Validation(_))));
+        assert_eq!(database.get_all_persons(), persons);
+        assert!(database.audit.is_empty());
+        assert!(!database.can_undo());
+    }
+
+    #[test]
+    fn restore_rejects_invalid_persons() {
+        let mut database = Database::new();
+        database.log_in("root", "root").unwrap();
+
+        let mut backup = database.backup().unwrap();
+        backup.persons[0].name.clear();
+
+        assert!(matches!(
+            database.restore_backup(&backup, RestoreMode::Merge),
+            Err(DbError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn restore_rejects_repeated_ids() {
+        let mut database = Database::new();
+        database.log_in("root", "root").unwrap();
+
+        let mut backup = database.backup().unwrap();
+        backup.persons.push(Person::new(1, "Twin"));
+
+        assert!(matches!(
+            database.preview_restore(&backup, RestoreMode::Replace),
+            Err(DbError::Duplicate(_))
+        ));
+    }
+}
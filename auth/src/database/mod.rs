@@ -0,0 +1,62 @@
+mod async_store;
+mod audit;
+mod backup;
+mod error;
+mod history;
+mod http;
+mod memory;
+mod query;
+mod store;
+mod trash;
+
+#[cfg(any(feature = "local-storage", feature = "indexed-db"))]
+mod persistent;
+#[cfg(any(feature = "local-storage", feature = "indexed-db"))]
+mod sync;
+
+#[cfg(feature = "indexed-db")]
+mod indexed_db;
+#[cfg(all(feature = "local-storage", not(feature = "indexed-db")))]
+mod local_storage;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub use async_store::*;
+pub use audit::*;
+pub use backup::*;
+pub use error::*;
+pub use memory::Database;
+pub use query::*;
+pub use store::*;
+pub use trash::*;
+
+pub type DbConn = Rc<RefCell<dyn Store>>;
+
+/// Opens a connection to the storage backend selected by cargo features.
+///
+/// `indexed-db` takes precedence over `local-storage`, when neither is
+/// enabled the data is kept in memory only.
+#[cfg(feature = "indexed-db")]
+pub fn open() -> DbConn {
+    indexed_db::IndexedDb::open()
+}
+
+#[cfg(all(feature = "local-storage", not(feature = "indexed-db")))]
+pub fn open() -> DbConn {
+    local_storage::LocalStorage::open().share()
+}
+
+#[cfg(not(any(feature = "local-storage", feature = "indexed-db")))]
+pub fn open() -> DbConn {
+    Rc::new(RefCell::new(memory::Database::new()))
+}
+
+/// Connects the pages to the server given in the URL of the page, see
+/// `http::base_url`, or to `db_conn` without one
+pub fn connect(db_conn: DbConn) -> AsyncConn {
+    match http::base_url() {
+        Some(base_url) => Rc::new(http::HttpStore::new(base_url)),
+        None => Rc::new(LocalStore::new(db_conn)),
+    }
+}
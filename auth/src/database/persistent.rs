@@ -0,0 +1,517 @@
+use crate::person::Person;
+use crate::user::{AuthenticatedUser, PasswordHash, Role, User};
+use std::cell::RefCell;
+use std::rc::Rc;
+use yew::Callback;
+
+use super::audit::{AuditEntry, AuditQuery};
+use super::backup::{Backup, BackupDiff, RestoreMode};
+use super::error::DbResult;
+use super::memory::Database;
+use super::query::{PersonsPage, PersonsQuery, ScoredPerson};
+use super::store::{
+    AuditStore, AuthStore, BackupStore, HistoryStore, LoggedIn, PersonStore, RecoveryStore,
+    SyncStore, TrashStore, UserStore,
+};
+use super::sync::SyncChannel;
+use super::trash::DeletedPerson;
+
+/// A place a `Database` is written to after each mutation, and read again
+/// when other tabs change it
+pub trait Backend {
+    fn save(&mut self, database: &Database) -> DbResult<()>;
+
+    /// The stored `Database` when another tab saved it since this one last
+    /// read or wrote it, `None` when it didn't or can't be read right away
+    fn load(&mut self) -> Option<Database>;
+
+    /// Whether the stored `Database` was read, backends reading it
+    /// asynchronously refuse to `save` before
+    fn is_loaded(&self) -> bool {
+        true
+    }
+
+    /// Why the stored `Database` couldn't be read, `save` refuses to write
+    /// over it meanwhile
+    fn unreadable(&self) -> Option<String> {
+        None
+    }
+
+    /// Forgets the stored `Database` couldn't be read, so `save` writes
+    /// over it again
+    fn discard_unreadable(&mut self) {}
+
+    /// Reads the `Database` another tab said it saved, handing it to
+    /// `PersistentStore::synced`. Backends that can't `load` right away
+    /// read it later on
+    fn reload(store: &Rc<RefCell<PersistentStore<Self>>>)
+    where
+        Self: Sized + 'static,
+    {
+        let database = store.borrow_mut().backend.load();
+
+        if let Some(database) = database {
+            PersistentStore::synced(store, database);
+        }
+    }
+}
+
+/// Keeps a `Database` in memory to answer queries and hands it to its
+/// `Backend` every time it changes. The other tabs of the app are told to
+/// read it from the backend again, and every change starts from what is
+/// stored, so tabs never hand out the same id twice
+#[derive(Debug)]
+pub struct PersistentStore<B: Backend> {
+    pub(super) database: Database,
+    pub(super) backend: B,
+    sync: Option<SyncChannel>,
+    /// Notified of the changes made by the other tabs
+    subscribers: Vec<Callback<()>>,
+}
+
+impl<B: Backend> PersistentStore<B> {
+    pub fn new(database: Database, backend: B) -> Self {
+        Self {
+            database,
+            backend,
+            sync: None,
+            subscribers: Vec::new(),
+        }
+    }
+
+    /// Applies `mutation` to the stored data and saves the result, the data
+    /// is left as it was when the backend can't save it
+    fn change<T, F>(&mut self, mutation: F) -> DbResult<T>
+    where
+        F: FnOnce(&mut Database) -> DbResult<T>,
+    {
+        self.refresh();
+
+        let before = self.database.clone();
+        let value = mutation(&mut self.database)?;
+
+        if let Err(err) = self.save() {
+            self.database = before;
+            return Err(err);
+        }
+
+        Ok(value)
+    }
+
+    /// Applies `mutation`, which only changes the sessions, like `change`.
+    /// While the stored data can't be read it is kept in memory instead,
+    /// so users can still log in and out of the seed data
+    fn change_session<T, F>(&mut self, mutation: F) -> DbResult<T>
+    where
+        F: FnOnce(&mut Database) -> DbResult<T>,
+    {
+        if self.backend.unreadable().is_some() {
+            return mutation(&mut self.database);
+        }
+
+        self.change(mutation)
+    }
+
+    /// Takes the data other tabs stored since this one last read or wrote
+    /// it. The undo history is dropped along with the data it referred to
+    fn refresh(&mut self) {
+        if let Some(database) = self.backend.load() {
+            self.database.replace(database);
+        }
+    }
+
+    fn save(&mut self) -> DbResult<()> {
+        self.backend.save(&self.database)?;
+
+        if let Some(sync) = &self.sync {
+            sync.publish();
+        }
+
+        Ok(())
+    }
+}
+
+impl<B: Backend + 'static> PersistentStore<B> {
+    /// Wraps the store to be shared by the pages, keeping it in sync with
+    /// the other tabs of the app
+    pub fn share(self) -> Rc<RefCell<Self>> {
+        let store = Rc::new(RefCell::new(self));
+        let handle = Rc::downgrade(&store);
+
+        store.borrow_mut().sync = SyncChannel::open(move || {
+            if let Some(store) = handle.upgrade() {
+                B::reload(&store);
+            }
+        });
+
+        store
+    }
+
+    /// Takes `database`, read from the backend after another tab changed
+    /// it, and tells the subscribers
+    pub(super) fn synced(store: &Rc<RefCell<Self>>, database: Database) {
+        store.borrow_mut().database.replace(database);
+        PersistentStore::notify(store);
+    }
+
+    /// Tells the subscribers the store changed without any of its methods
+    /// being called
+    pub(super) fn notify(store: &Rc<RefCell<Self>>) {
+        // Released before notifying, subscribers read the store
+        let subscribers = store.borrow().subscribers.clone();
+
+        for subscriber in subscribers {
+            subscriber.emit(());
+        }
+    }
+}
+
+impl<B: Backend> AuthStore for PersistentStore<B> {
+    fn log_in(&mut self, username: &str, password: &str) -> DbResult<LoggedIn> {
+        self.change_session(|database| database.log_in(username, password))
+    }
+
+    fn resume(&mut self, username: &str, token: &str) -> DbResult<AuthenticatedUser> {
+        self.refresh();
+        self.database.resume(username, token)
+    }
+
+    fn log_out(&mut self) {
+        let logged_out = self.change_session(|database| {
+            database.log_out();
+            Ok(())
+        });
+
+        if let Err(err) = logged_out {
+            // The session stays stored, but this tab stops acting anyway
+            self.database.log_out();
+            web_sys::console::error_1(&err.to_string().into());
+        }
+    }
+}
+
+impl<B: Backend> PersonStore for PersistentStore<B> {
+    fn get_all_persons(&self) -> Vec<Person> {
+        self.database.get_all_persons()
+    }
+
+    fn get_persons_by_name(&self, partial: &str) -> Vec<Person> {
+        self.database.get_persons_by_name(partial)
+    }
+
+    fn search_persons(&self, text: &str) -> Vec<Person> {
+        self.database.search_persons(text)
+    }
+
+    fn fuzzy_search_persons(&self, text: &str) -> Vec<ScoredPerson> {
+        self.database.fuzzy_search_persons(text)
+    }
+
+    fn query_persons(&self, query: &PersonsQuery) -> PersonsPage {
+        self.database.query_persons(query)
+    }
+
+    fn get_person_by_id(&self, id: u32) -> Option<Person> {
+        self.database.get_person_by_id(id)
+    }
+
+    fn insert(&mut self, person: Person) -> DbResult<u32> {
+        self.change(|database| database.insert(person))
+    }
+
+    fn update(&mut self, person: Person) -> DbResult<()> {
+        self.change(|database| database.update(person))
+    }
+
+    fn delete(&mut self, id: u32, version: Option<u32>) -> DbResult<()> {
+        self.change(|database| database.delete(id, version))
+    }
+
+    fn export_persons(&self, ids: &[u32]) -> DbResult<Vec<Person>> {
+        self.database.export_persons(ids)
+    }
+
+    fn import_persons(
+        &mut self,
+        inserts: Vec<Person>,
+        updates: Vec<Person>,
+    ) -> DbResult<(usize, usize)> {
+        self.change(|database| database.import_persons(inserts, updates))
+    }
+}
+
+impl<B: Backend> TrashStore for PersistentStore<B> {
+    fn get_trash(&self) -> Vec<DeletedPerson> {
+        self.database.get_trash()
+    }
+
+    fn restore(&mut self, id: u32) -> DbResult<()> {
+        self.change(|database| database.restore(id))
+    }
+
+    fn purge(&mut self, id: u32) -> DbResult<()> {
+        self.change(|database| database.purge(id))
+    }
+
+    fn retention_days(&self) -> u32 {
+        self.database.retention_days()
+    }
+
+    fn set_retention_days(&mut self, days: u32) -> DbResult<()> {
+        self.change(|database| database.set_retention_days(days))
+    }
+}
+
+impl<B: Backend> UserStore for PersistentStore<B> {
+    fn get_all_users(&self) -> Vec<User> {
+        self.database.get_all_users()
+    }
+
+    fn get_user_by_username(&self, username: &str) -> Option<User> {
+        self.database.get_user_by_username(username)
+    }
+
+    fn insert_user(
+        &mut self,
+        username: &str,
+        password: PasswordHash,
+        roles: Vec<Role>,
+    ) -> DbResult<()> {
+        self.change(|database| database.insert_user(username, password, roles))
+    }
+
+    fn delete_user(&mut self, username: &str) -> DbResult<()> {
+        self.change(|database| database.delete_user(username))
+    }
+
+    fn set_user_disabled(&mut self, username: &str, disabled: bool) -> DbResult<()> {
+        self.change(|database| database.set_user_disabled(username, disabled))
+    }
+
+    fn reset_password(&mut self, username: &str, password: PasswordHash) -> DbResult<()> {
+        self.change(|database| database.reset_password(username, password))
+    }
+
+    fn grant_role(&mut self, username: &str, role: Role) -> DbResult<()> {
+        self.change(|database| database.grant_role(username, role))
+    }
+
+    fn revoke_role(&mut self, username: &str, role: Role) -> DbResult<()> {
+        self.change(|database| database.revoke_role(username, role))
+    }
+}
+
+impl<B: Backend> AuditStore for PersistentStore<B> {
+    fn query_audit(&self, query: &AuditQuery) -> Vec<AuditEntry> {
+        self.database.query_audit(query)
+    }
+}
+
+impl<B: Backend> HistoryStore for PersistentStore<B> {
+    fn begin_batch(&mut self) {
+        self.database.begin_batch()
+    }
+
+    fn end_batch(&mut self) {
+        self.database.end_batch()
+    }
+
+    fn can_undo(&self) -> bool {
+        self.database.can_undo()
+    }
+
+    fn can_redo(&self) -> bool {
+        self.database.can_redo()
+    }
+
+    fn undo(&mut self) -> DbResult<String> {
+        self.change(|database| database.undo())
+    }
+
+    fn redo(&mut self) -> DbResult<String> {
+        self.change(|database| database.redo())
+    }
+}
+
+impl<B: Backend> SyncStore for PersistentStore<B> {
+    fn subscribe(&mut self, callback: Callback<()>) {
+        self.subscribers.push(callback);
+    }
+
+    fn is_loaded(&self) -> bool {
+        self.backend.is_loaded()
+    }
+}
+
+impl<B: Backend> RecoveryStore for PersistentStore<B> {
+    fn unreadable(&self) -> Option<String> {
+        self.backend.unreadable()
+    }
+
+    fn discard_unreadable(&mut self) -> DbResult<()> {
+        self.backend.discard_unreadable();
+        self.save()
+    }
+}
+
+impl<B: Backend> BackupStore for PersistentStore<B> {
+    fn backup(&self) -> DbResult<Backup> {
+        self.database.backup()
+    }
+
+    fn preview_restore(&self, backup: &Backup, mode: RestoreMode) -> DbResult<BackupDiff> {
+        self.database.preview_restore(backup, mode)
+    }
+
+    fn restore_backup(&mut self, backup: &Backup, mode: RestoreMode) -> DbResult<BackupDiff> {
+        self.change(|database| database.restore_backup(backup, mode))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DbError;
+
+    /// A `Backend` whose stored value is shared by the stores of every tab
+    #[derive(Debug, Default)]
+    struct SharedBackend {
+        stored: Rc<RefCell<Option<String>>>,
+        seen: Option<String>,
+        /// Makes every save fail, like a full storage
+        full: bool,
+        unreadable: Option<String>,
+    }
+
+    impl Backend for SharedBackend {
+        fn save(&mut self, database: &Database) -> DbResult<()> {
+            if self.full {
+                return Err(DbError::Storage("The storage is full".to_string()));
+            }
+
+            if self.unreadable.is_some() {
+                return Err(DbError::Storage("The data can't be read".to_string()));
+            }
+
+            let json = serde_json::to_string(database).unwrap();
+
+            *self.stored.borrow_mut() = Some(json.clone());
+            self.seen = Some(json);
+
+            Ok(())
+        }
+
+        fn load(&mut self) -> Option<Database> {
+            let json = self.stored.borrow().clone()?;
+
+            if self.seen.as_ref() == Some(&json) {
+                return None;
+            }
+
+            self.seen = Some(json.clone());
+            serde_json::from_str(&json).ok()
+        }
+
+        fn unreadable(&self) -> Option<String> {
+            self.unreadable.clone()
+        }
+
+        fn discard_unreadable(&mut self) {
+            self.unreadable = None;
+        }
+    }
+
+    fn tab(stored: &Rc<RefCell<Option<String>>>) -> PersistentStore<SharedBackend> {
+        let backend = SharedBackend {
+            stored: stored.clone(),
+            ..SharedBackend::default()
+        };
+        let mut store = PersistentStore::new(Database::new(), backend);
+
+        store.log_in("root", "root").unwrap();
+        store
+    }
+
+    #[test]
+    fn tabs_share_the_stored_data_and_ids() {
+        let stored = Rc::new(RefCell::new(None));
+        let mut first = tab(&stored);
+        let mut second = tab(&stored);
+
+        let ana = first.insert(Person::new(0, "Ana")).unwrap();
+        let bea = second.insert(Person::new(0, "Bea")).unwrap();
+
+        assert_ne!(ana, bea);
+        assert!(second.get_person_by_id(ana).is_some());
+        assert!(first.get_person_by_id(bea).is_none());
+
+        let cleo = first.insert(Person::new(0, "Cleo")).unwrap();
+
+        assert!(cleo > bea);
+        assert!(first.get_person_by_id(bea).is_some());
+    }
+
+    #[test]
+    fn undo_history_is_kept_until_another_tab_changes_the_data() {
+        let stored = Rc::new(RefCell::new(None));
+        let mut first = tab(&stored);
+        let mut second = tab(&stored);
+
+        first.insert(Person::new(0, "Ana")).unwrap();
+        assert!(first.can_undo());
+
+        first.insert(Person::new(0, "Bea")).unwrap();
+        first.undo().unwrap();
+        assert!(first.can_redo());
+
+        second.insert(Person::new(0, "Cleo")).unwrap();
+        first.insert(Person::new(0, "Dora")).unwrap();
+        assert!(first.undo().is_ok());
+        assert!(first.undo().is_err());
+    }
+
+    #[test]
+    fn changes_the_backend_can_not_save_are_undone() {
+        let stored = Rc::new(RefCell::new(None));
+        let mut store = tab(&stored);
+        let persons = store.get_all_persons();
+
+        store.backend.full = true;
+
+        assert!(store.insert(Person::new(0, "Ana")).is_err());
+        assert_eq!(store.get_all_persons(), persons);
+        assert!(!store.can_undo());
+
+        store.backend.full = false;
+
+        let id = store.insert(Person::new(0, "Ana")).unwrap();
+        assert!(store.get_person_by_id(id).is_some());
+    }
+
+    #[test]
+    fn sessions_are_kept_in_memory_until_the_unreadable_data_is_discarded() {
+        let stored = Rc::new(RefCell::new(Some("{not json".to_string())));
+        let backend = SharedBackend {
+            stored: stored.clone(),
+            seen: stored.borrow().clone(),
+            unreadable: Some("expected value".to_string()),
+            ..SharedBackend::default()
+        };
+        let mut store = PersistentStore::new(Database::new(), backend);
+
+        let logged_in = store.log_in("root", "root").unwrap();
+        assert!(store.resume("root", &logged_in.token).is_ok());
+        assert!(store.insert(Person::new(0, "Zelda")).is_err());
+        store.log_out();
+        assert_eq!(stored.borrow().as_deref(), Some("{not json"));
+
+        store.log_in("root", "root").unwrap();
+        store.discard_unreadable().unwrap();
+        assert!(store.unreadable().is_none());
+        assert!(store.insert(Person::new(0, "Zelda")).is_ok());
+        assert!(tab(&stored)
+            .get_all_persons()
+            .iter()
+            .any(|p| p.name == "Zelda"));
+    }
+}
@@ -0,0 +1,101 @@
+use crate::person::Person;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Columns persons can be sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SortColumn {
+    Id,
+    Name,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PersonsSort {
+    pub column: SortColumn,
+    pub ascending: bool,
+}
+
+impl PersonsSort {
+    /// Sorting by `column`, or the next order of this sort if it already
+    /// sorts by `column`
+    pub fn toggle(sort: Option<PersonsSort>, column: SortColumn) -> PersonsSort {
+        match sort {
+            Some(sort) if sort.column == column => PersonsSort {
+                column,
+                ascending: !sort.ascending,
+            },
+            _ => PersonsSort {
+                column,
+                ascending: true,
+            },
+        }
+    }
+
+    /// Names are compared ignoring case, ties are broken by id so the
+    /// order is stable
+    pub fn compare(&self, a: &Person, b: &Person) -> Ordering {
+        let ordering = match self.column {
+            SortColumn::Id => a.id.cmp(&b.id),
+            SortColumn::Name => a
+                .name
+                .to_lowercase()
+                .cmp(&b.name.to_lowercase())
+                .then(a.id.cmp(&b.id)),
+        };
+
+        if self.ascending {
+            ordering
+        } else {
+            ordering.reverse()
+        }
+    }
+}
+
+/// Sizes offered to page through the persons
+pub const PAGE_SIZES: [usize; 4] = [10, 25, 50, 100];
+
+/// A page of the persons matching `text`, sorted by `sort`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PersonsQuery {
+    pub text: String,
+    /// Accents are always ignored, case only when this is not set
+    pub match_case: bool,
+    /// Ranks names by similarity to `text` instead of matching substrings,
+    /// `sort` and `match_case` are ignored in this mode
+    pub fuzzy: bool,
+    pub sort: Option<PersonsSort>,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl Default for PersonsQuery {
+    fn default() -> Self {
+        Self {
+            text: String::default(),
+            match_case: false,
+            fuzzy: false,
+            sort: None,
+            offset: 0,
+            limit: PAGE_SIZES[0],
+        }
+    }
+}
+
+/// Result of a `PersonsQuery`, `total` counts every match and not only
+/// the ones in `persons`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PersonsPage {
+    pub persons: Vec<Person>,
+    pub total: usize,
+    /// Similarity of each person in the page by id, only for fuzzy queries
+    pub scores: HashMap<u32, f32>,
+}
+
+/// A person found by a fuzzy search along with how similar it is to the
+/// search, from `0.0` to `1.0`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoredPerson {
+    pub person: Person,
+    pub score: f32,
+}
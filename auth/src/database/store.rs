@@ -0,0 +1,213 @@
+use crate::person::Person;
+use crate::user::{AuthenticatedUser, PasswordHash, Role, User};
+use std::fmt::Debug;
+use yew::Callback;
+
+use super::audit::{AuditEntry, AuditQuery};
+use super::backup::{Backup, BackupDiff, RestoreMode};
+use super::error::DbResult;
+use super::query::{PersonsPage, PersonsQuery, ScoredPerson};
+use super::trash::DeletedPerson;
+
+/// A user who just logged in, along with the token of the session
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoggedIn {
+    pub user: AuthenticatedUser,
+    pub token: String,
+}
+
+/// Who is acting on the data, every other operation is authorized
+/// against the privileges this user has at the time
+pub trait AuthStore {
+    /// Verifies the credentials and acts as the user from then on, starting
+    /// a session that `resume` can pick up with the returned token
+    fn log_in(&mut self, username: &str, password: &str) -> DbResult<LoggedIn>;
+
+    /// Acts as `username` again without a password, for sessions that
+    /// survived a reload. The token must be the one `log_in` returned for
+    /// a session that didn't end yet
+    fn resume(&mut self, username: &str, token: &str) -> DbResult<AuthenticatedUser>;
+
+    /// Stops acting as the user and ends the session
+    fn log_out(&mut self);
+}
+
+/// Operations over the persons kept by a storage backend, reading needs
+/// `CanRead` and nothing is returned without it
+pub trait PersonStore {
+    fn get_all_persons(&self) -> Vec<Person>;
+
+    /// Persons whose name contains `partial`, ignoring case and accents
+    fn get_persons_by_name(&self, partial: &str) -> Vec<Person>;
+
+    /// Persons with any field containing `text`, ignoring case and accents
+    fn search_persons(&self, text: &str) -> Vec<Person>;
+
+    /// Persons whose name resembles `text` in spelling or sound, ranked
+    /// from the most to the least similar
+    fn fuzzy_search_persons(&self, text: &str) -> Vec<ScoredPerson>;
+
+    /// A sorted slice of the persons matching the query, only the persons
+    /// in the page are cloned
+    fn query_persons(&self, query: &PersonsQuery) -> PersonsPage;
+
+    fn get_person_by_id(&self, id: u32) -> Option<Person>;
+
+    /// Stores a new person, its `id` is ignored and the assigned one is
+    /// returned instead
+    fn insert(&mut self, person: Person) -> DbResult<u32>;
+
+    /// Replaces the stored person, which must still have the `version` of
+    /// `person`, and increases its version
+    fn update(&mut self, person: Person) -> DbResult<()>;
+
+    /// Moves the person to the trash, where it can be restored from. With a
+    /// `version`, the stored person must still have it
+    fn delete(&mut self, id: u32, version: Option<u32>) -> DbResult<()>;
+
+    /// The persons with the given ids in the same order, ids that don't
+    /// exist are skipped. It needs `CanExport`
+    fn export_persons(&self, ids: &[u32]) -> DbResult<Vec<Person>>;
+
+    /// Inserts and updates all the persons as a single step that is undone
+    /// at once, nothing is changed if any of them is invalid. Updates
+    /// overwrite whatever version is stored. Returns the number of persons
+    /// inserted and updated
+    fn import_persons(
+        &mut self,
+        inserts: Vec<Person>,
+        updates: Vec<Person>,
+    ) -> DbResult<(usize, usize)>;
+}
+
+/// Persons deleted but not purged yet, they are purged automatically once
+/// they were deleted more than `retention_days` ago
+pub trait TrashStore {
+    /// Deleted persons, the most recently deleted first
+    fn get_trash(&self) -> Vec<DeletedPerson>;
+
+    /// Brings a deleted person back, it needs `CanCreate`
+    fn restore(&mut self, id: u32) -> DbResult<()>;
+
+    /// Removes a deleted person for good, it needs `CanDelete`
+    fn purge(&mut self, id: u32) -> DbResult<()>;
+
+    fn retention_days(&self) -> u32;
+
+    fn set_retention_days(&mut self, days: u32) -> DbResult<()>;
+}
+
+/// Operations over the users kept by a storage backend, they all need
+/// `CanManageUsers`
+pub trait UserStore {
+    fn get_all_users(&self) -> Vec<User>;
+
+    fn get_user_by_username(&self, username: &str) -> Option<User>;
+
+    fn insert_user(
+        &mut self,
+        username: &str,
+        password: PasswordHash,
+        roles: Vec<Role>,
+    ) -> DbResult<()>;
+
+    fn delete_user(&mut self, username: &str) -> DbResult<()>;
+
+    fn set_user_disabled(&mut self, username: &str, disabled: bool) -> DbResult<()>;
+
+    fn reset_password(&mut self, username: &str, password: PasswordHash) -> DbResult<()>;
+
+    fn grant_role(&mut self, username: &str, role: Role) -> DbResult<()>;
+
+    fn revoke_role(&mut self, username: &str, role: Role) -> DbResult<()>;
+}
+
+/// Record of every change made to the persons
+pub trait AuditStore {
+    /// Entries matching `query`, newest first, it needs `CanAudit`
+    fn query_audit(&self, query: &AuditQuery) -> Vec<AuditEntry>;
+}
+
+/// Undoing and redoing the changes made to the persons by the acting user,
+/// each one authorized like the change it makes
+pub trait HistoryStore {
+    /// Groups the following changes into a single step, until `end_batch`
+    fn begin_batch(&mut self);
+
+    fn end_batch(&mut self);
+
+    fn can_undo(&self) -> bool;
+
+    fn can_redo(&self) -> bool;
+
+    /// Reverts the last step, returning a description of it
+    fn undo(&mut self) -> DbResult<String>;
+
+    /// Makes the last undone step again, returning a description of it
+    fn redo(&mut self) -> DbResult<String>;
+}
+
+/// Full copies of the data, they all need `CanManageUsers`
+pub trait BackupStore {
+    fn backup(&self) -> DbResult<Backup>;
+
+    /// What restoring `backup` would change, without changing anything
+    fn preview_restore(&self, backup: &Backup, mode: RestoreMode) -> DbResult<BackupDiff>;
+
+    /// Restores `backup` as long as the acting user can still manage users
+    /// afterwards, the changes to the persons are audited but can't be
+    /// undone
+    fn restore_backup(&mut self, backup: &Backup, mode: RestoreMode) -> DbResult<BackupDiff>;
+}
+
+/// Changes other tabs of the app make to the same data
+pub trait SyncStore {
+    /// Calls `callback` every time another tab changes the data, once the
+    /// change can be read from this store
+    fn subscribe(&mut self, callback: Callback<()>);
+
+    /// Whether the stored data was read, the subscribers are called once it
+    /// is. Until then the data is the seed data and changes can't be saved
+    fn is_loaded(&self) -> bool;
+}
+
+/// Stored data the store couldn't read, which it never saves over until
+/// told to discard it
+pub trait RecoveryStore {
+    /// Why the stored data couldn't be read, `None` when it was read
+    fn unreadable(&self) -> Option<String>;
+
+    /// Saves the data in use over the stored data that couldn't be read,
+    /// which is lost for good
+    fn discard_unreadable(&mut self) -> DbResult<()>;
+}
+
+/// A complete storage backend, the pages only talk to the data through
+/// this trait so they don't care where it lives
+pub trait Store:
+    AuthStore
+    + PersonStore
+    + TrashStore
+    + UserStore
+    + AuditStore
+    + HistoryStore
+    + BackupStore
+    + SyncStore
+    + RecoveryStore
+    + Debug
+{
+}
+
+impl<T> Store for T where
+    T: AuthStore
+        + PersonStore
+        + TrashStore
+        + UserStore
+        + AuditStore
+        + HistoryStore
+        + BackupStore
+        + SyncStore
+        + RecoveryStore
+        + Debug
+{
+}
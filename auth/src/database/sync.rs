@@ -0,0 +1,56 @@
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{BroadcastChannel, MessageEvent};
+
+/// Name of the `BroadcastChannel` shared by the tabs of the app
+const CHANNEL_NAME: &str = "yew-app-samples.auth.sync";
+
+/// Message telling the other tabs to read the stored data again
+const CHANGED: &str = "changed";
+
+/// Tells the other tabs of the app every time the `Database` is saved, and
+/// hands over the notices they send. The data itself is only exchanged
+/// through the storage. A tab doesn't get the messages it sends itself
+#[derive(Debug)]
+pub struct SyncChannel {
+    channel: BroadcastChannel,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+}
+
+impl SyncChannel {
+    /// Calls `on_changed` every time another tab saves the `Database`,
+    /// `None` when the browser has no `BroadcastChannel`
+    pub fn open<F>(mut on_changed: F) -> Option<Self>
+    where
+        F: FnMut() + 'static,
+    {
+        let channel = BroadcastChannel::new(CHANNEL_NAME).ok()?;
+        let on_message = Closure::wrap(Box::new(move |event: MessageEvent| {
+            if event.data().as_string().as_deref() == Some(CHANGED) {
+                on_changed();
+            }
+        }) as Box<dyn FnMut(MessageEvent)>);
+
+        channel.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+        Some(Self {
+            channel,
+            _on_message: on_message,
+        })
+    }
+
+    pub fn publish(&self) {
+        let sent = self.channel.post_message(&JsValue::from_str(CHANGED));
+
+        if sent.is_err() {
+            web_sys::console::error_1(&"Unable to send the changes to the other tabs".into());
+        }
+    }
+}
+
+impl Drop for SyncChannel {
+    fn drop(&mut self) {
+        self.channel.set_onmessage(None);
+        self.channel.close();
+    }
+}
@@ -0,0 +1,14 @@
+use crate::person::Person;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Days deleted persons are kept in the trash unless configured otherwise
+pub const DEFAULT_RETENTION_DAYS: u32 = 30;
+
+/// A person moved to the trash, it can be restored until it is purged
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeletedPerson {
+    pub person: Person,
+    pub deleted_at: DateTime<Utc>,
+    pub deleted_by: String,
+}
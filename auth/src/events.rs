@@ -0,0 +1,35 @@
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+use web_sys::Event;
+use yew::Callback;
+
+/// Emits `callback` every time `event` is dispatched to the window until
+/// it is dropped
+pub struct WindowListener {
+    event: &'static str,
+    closure: Closure<dyn FnMut(Event)>,
+}
+
+impl WindowListener {
+    pub fn new(event: &'static str, callback: Callback<Event>) -> Self {
+        let closure = Closure::wrap(Box::new(move |e| callback.emit(e)) as Box<dyn FnMut(Event)>);
+
+        if let Some(window) = web_sys::window() {
+            let _ =
+                window.add_event_listener_with_callback(event, closure.as_ref().unchecked_ref());
+        }
+
+        Self { event, closure }
+    }
+}
+
+impl Drop for WindowListener {
+    fn drop(&mut self) {
+        if let Some(window) = web_sys::window() {
+            let _ = window.remove_event_listener_with_callback(
+                self.event,
+                self.closure.as_ref().unchecked_ref(),
+            );
+        }
+    }
+}
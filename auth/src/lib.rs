@@ -1,35 +1,322 @@
 #![recursion_limit = "1024"]
 
-use crate::pages::{Login, Person, PersonsList};
+use crate::events::WindowListener;
+use crate::pages::{
+    view_loading, AuditLog, BackupRestore, ImportPersons, Loading, Login, Person, PersonsList,
+    Trash, UsersList,
+};
+use crate::route::Route;
+use crate::session::Session;
 
+use chrono::Utc;
+use std::time::Duration;
 use wasm_bindgen::prelude::*;
 use yew::html;
 use yew::prelude::*;
+use yew::services::interval::{IntervalService, IntervalTask};
+use yew::services::DialogService;
 
-mod database;
+// The data layer is public so the `server` crate runs the same `Database`
+pub mod database;
+mod download;
+mod events;
 mod pages;
-mod person;
-mod user;
+pub mod person;
+mod person_csv;
+// Shared with the `server` crate, which reads the query strings of the app
+pub mod query_string;
+mod route;
+mod search;
+mod session;
+pub mod user;
+mod validation;
 
 enum Page {
     Login,
     PersonsList,
-    OnePerson(Option<person::Person>),
+    OnePerson(Option<u32>),
+    ImportPersons,
+    Trash,
+    UsersList,
+    AuditLog,
+    BackupRestore,
 }
 
+/// Events the user causes while using the page, any of them postpones
+/// the logout for inactivity
+const ACTIVITY_EVENTS: [&str; 4] = ["click", "keydown", "mousemove", "scroll"];
+
 struct AuthApp {
     page: Page,
-    current_user: Option<user::User>,
-    can_write: bool,
+    current_user: Option<user::AuthenticatedUser>,
+    session: Option<Session>,
+    /// Seconds left before the automatic logout, once it is close
+    logout_warning: Option<i64>,
+    persons_sort: Option<database::PersonsSort>,
+    /// Route asked for before logging in, shown once the user logs in
+    pending_route: Option<Route>,
+    /// Restoring the saved session, no page is shown meanwhile
+    resuming: Loading,
+    /// Request restoring the session, dropping it cancels the request
+    resume_request: Option<database::Pending>,
+    /// Waiting for the store to read the stored data, the session is
+    /// restored once it did
+    loading_data: bool,
     db_conn: database::DbConn,
+    /// Number of times other tabs changed the data, pages are refreshed on
+    /// each of them
+    sync_count: usize,
+    /// The data of the pages, on a server when the URL names one
+    async_conn: database::AsyncConn,
+    _route_listener: WindowListener,
+    _activity_listeners: Vec<WindowListener>,
+    _session_ticker: IntervalTask,
     link: ComponentLink<Self>,
 }
 
 enum Msg {
-    LoggedIn(user::User),
-    ChangeUser,
-    GoToOnePersonPage(Option<person::Person>),
+    LoggedIn(database::LoggedIn),
+    RestoreSession,
+    Resumed(Session, database::DbResult<user::AuthenticatedUser>),
+    LogOut,
+    GoToOnePersonPage(Option<u32>),
     GoToPersonsListPage,
+    GoToImportPage,
+    GoToTrashPage,
+    GoToUsersListPage,
+    GoToAuditLogPage,
+    GoToBackupPage,
+    SortPersons(Option<database::PersonsSort>),
+    RouteChanged(Option<Route>),
+    Synced,
+    DiscardUnreadable,
+    Activity,
+    CheckSession,
+}
+
+impl AuthApp {
+    fn can(&self, privilege: user::DbPrivilege) -> bool {
+        self.current_user
+            .as_ref()
+            .is_some_and(|user| user.has_privilege(privilege))
+    }
+
+    /// Restores the user of the saved session, as long as it still exists
+    /// and is enabled, the page of the URL is shown once it is done
+    fn restore_session(&mut self) {
+        let session = match Session::restore() {
+            Some(session) => session,
+            None => return self.show(route::current()),
+        };
+        let (username, token) = (session.username.clone(), session.token.clone());
+        let callback = self
+            .link
+            .callback(move |user| Msg::Resumed(session.clone(), user));
+
+        self.resuming = Loading::Busy;
+        self.resume_request = Some(self.async_conn.resume(&username, &token, callback));
+    }
+
+    fn is_remote(&self) -> bool {
+        self.async_conn.is_remote()
+    }
+
+    /// Forgets the user and everything kept for them
+    fn log_out(&mut self) {
+        self.async_conn.log_out();
+        Session::clear();
+        self.session = None;
+        self.logout_warning = None;
+        self.current_user = None;
+        self.persons_sort = None;
+        self.pending_route = None;
+        self.navigate(Route::Login);
+    }
+
+    /// Buttons to the other pages the user is allowed to visit
+    fn view_navigation(&self) -> Html {
+        let on_persons_page = matches!(
+            self.page,
+            Page::PersonsList | Page::OnePerson(_) | Page::ImportPersons
+        );
+        let on_trash_page = matches!(self.page, Page::Trash);
+        let on_users_page = matches!(self.page, Page::UsersList);
+        let on_audit_page = matches!(self.page, Page::AuditLog);
+        let on_backup_page = matches!(self.page, Page::BackupRestore);
+
+        html! {
+            <>
+                {
+                    if on_persons_page {
+                        html! {}
+                    } else {
+                        html! {
+                            <button onclick=self.link.callback(|_| Msg::GoToPersonsListPage)>
+                                {"Persons"}
+                            </button>
+                        }
+                    }
+                }
+                {
+                    if on_trash_page || self.is_remote() {
+                        html! {}
+                    } else {
+                        html! {
+                            <button onclick=self.link.callback(|_| Msg::GoToTrashPage)>
+                                {"Trash"}
+                            </button>
+                        }
+                    }
+                }
+                {
+                    if on_users_page
+                        || self.is_remote()
+                        || !self.can(user::DbPrivilege::CanManageUsers)
+                    {
+                        html! {}
+                    } else {
+                        html! {
+                            <button onclick=self.link.callback(|_| Msg::GoToUsersListPage)>
+                                {"Users"}
+                            </button>
+                        }
+                    }
+                }
+                {
+                    if on_audit_page || self.is_remote() || !self.can(user::DbPrivilege::CanAudit) {
+                        html! {}
+                    } else {
+                        html! {
+                            <button onclick=self.link.callback(|_| Msg::GoToAuditLogPage)>
+                                {"Audit Log"}
+                            </button>
+                        }
+                    }
+                }
+                {
+                    if on_backup_page
+                        || self.is_remote()
+                        || !self.can(user::DbPrivilege::CanManageUsers)
+                    {
+                        html! {}
+                    } else {
+                        html! {
+                            <button onclick=self.link.callback(|_| Msg::GoToBackupPage)>
+                                {"Backup"}
+                            </button>
+                        }
+                    }
+                }
+            </>
+        }
+    }
+
+    /// Warns the stored data couldn't be read, offering to discard it
+    fn view_unreadable(&self) -> Html {
+        let err = match self.db_conn.borrow().unreadable() {
+            Some(err) if !self.is_remote() => err,
+            _ => return html! {},
+        };
+
+        html! {
+            <p class="storage-error">
+                {format!(
+                    "The stored data can't be read ({}), the seed data is shown and no change \
+                     but logging in and out can be saved ",
+                    err
+                )}
+                <button onclick=self.link.callback(|_| Msg::DiscardUnreadable)>
+                    {"Discard the Stored Data"}
+                </button>
+            </p>
+        }
+    }
+
+    fn view_logout_warning(&self) -> Html {
+        let (seconds, session) = match (self.logout_warning, &self.session) {
+            (Some(seconds), Some(session)) => (seconds, session),
+            _ => return html! {},
+        };
+
+        // Activity can't extend the lifetime of a session
+        if session.ends_idle() {
+            html! {
+                <p class="session-warning">
+                    {format!("You will be logged out in {} seconds for inactivity ", seconds)}
+                    <button onclick=self.link.callback(|_| Msg::Activity)>
+                        {"Stay Logged In"}
+                    </button>
+                </p>
+            }
+        } else {
+            html! {
+                <p class="session-warning">
+                    {format!("Your session ends in {} seconds, you will have to log in again", seconds)}
+                </p>
+            }
+        }
+    }
+
+    /// Moves to `route` through the URL, the page is shown once the URL
+    /// changes
+    fn navigate(&mut self, route: Route) {
+        if route::current() == Some(route) {
+            self.show(Some(route));
+        } else {
+            route::push(route);
+        }
+    }
+
+    /// Shows the page of `route`, redirecting visitors not logged in to
+    /// the login page and unknown or forbidden routes to a default one
+    fn show(&mut self, route: Option<Route>) {
+        let route = match route {
+            Some(route) => route,
+            None if self.current_user.is_some() => return route::replace(Route::PersonsList),
+            None => return route::replace(Route::Login),
+        };
+
+        if route.requires_login() && self.current_user.is_none() {
+            self.pending_route = Some(route);
+
+            return route::replace(Route::Login);
+        }
+
+        // Pages of the operations only the `Database` in the browser offers
+        let is_local_only = matches!(
+            route,
+            Route::ImportPersons
+                | Route::Trash
+                | Route::UsersList
+                | Route::AuditLog
+                | Route::Backup
+        );
+
+        if is_local_only && self.is_remote() {
+            return route::replace(Route::PersonsList);
+        }
+
+        self.page = match route {
+            Route::Login => Page::Login,
+            Route::PersonsList => Page::PersonsList,
+            Route::NewPerson => Page::OnePerson(None),
+            Route::Person(id) => Page::OnePerson(Some(id)),
+            Route::ImportPersons
+                if self.can(user::DbPrivilege::CanCreate)
+                    || self.can(user::DbPrivilege::CanUpdate) =>
+            {
+                Page::ImportPersons
+            }
+            Route::ImportPersons => return route::replace(Route::PersonsList),
+            Route::Trash => Page::Trash,
+            Route::UsersList if self.can(user::DbPrivilege::CanManageUsers) => Page::UsersList,
+            Route::AuditLog if self.can(user::DbPrivilege::CanAudit) => Page::AuditLog,
+            Route::Backup if self.can(user::DbPrivilege::CanManageUsers) => Page::BackupRestore,
+            Route::UsersList | Route::AuditLog | Route::Backup => {
+                return route::replace(Route::PersonsList)
+            }
+        };
+    }
 }
 
 impl Component for AuthApp {
@@ -37,25 +324,150 @@ impl Component for AuthApp {
     type Properties = ();
 
     fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
-        Self {
+        let db_conn = database::open();
+
+        db_conn
+            .borrow_mut()
+            .subscribe(link.callback(|_| Msg::Synced));
+
+        let mut app = Self {
             page: Page::Login,
             current_user: None,
-            can_write: false,
-            db_conn: database::Database::new_thread_safe(),
+            session: None,
+            logout_warning: None,
+            persons_sort: None,
+            pending_route: None,
+            resuming: Loading::Idle,
+            resume_request: None,
+            loading_data: false,
+            async_conn: database::connect(db_conn.clone()),
+            db_conn,
+            sync_count: 0,
+            _route_listener: route::listen(link.callback(Msg::RouteChanged)),
+            _activity_listeners: ACTIVITY_EVENTS
+                .iter()
+                .map(|event| WindowListener::new(event, link.callback(|_| Msg::Activity)))
+                .collect(),
+            _session_ticker: IntervalService::spawn(
+                Duration::from_secs(1),
+                link.callback(|_| Msg::CheckSession),
+            ),
             link,
+        };
+
+        if app.is_remote() || app.db_conn.borrow().is_loaded() {
+            app.restore_session();
+        } else {
+            app.loading_data = true;
+            app.resuming = Loading::Busy;
         }
+
+        app
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         match msg {
-            Msg::LoggedIn(user) => {
-                self.page = Page::PersonsList;
-                self.current_user = Some(user.clone());
-                self.can_write = user.privileges.contains(&user::DbPrivilege::CanWrite);
-            },
-            Msg::ChangeUser => self.page = Page::Login,
-            Msg::GoToOnePersonPage(person) => self.page = Page::OnePerson(person),
-            Msg::GoToPersonsListPage => self.page = Page::PersonsList,
+            Msg::LoggedIn(logged_in) => {
+                self.session = Some(Session::start(&logged_in.user.username, logged_in.token));
+                self.current_user = Some(logged_in.user);
+
+                let route = self.pending_route.take().unwrap_or(Route::PersonsList);
+
+                self.navigate(route);
+            }
+            Msg::RestoreSession => self.restore_session(),
+            Msg::Resumed(session, user) => {
+                self.resume_request = None;
+                self.resuming = Loading::Idle;
+
+                match user {
+                    Ok(user) => {
+                        self.current_user = Some(user);
+                        self.session = Some(session);
+                    }
+                    Err(err) => match Loading::from_error(&err) {
+                        // The server may come back, the session is kept
+                        Some(loading) => {
+                            self.resuming = loading;
+
+                            return true;
+                        }
+                        None => Session::clear(),
+                    },
+                }
+
+                self.show(route::current());
+            }
+            Msg::LogOut => self.log_out(),
+            Msg::GoToOnePersonPage(id) => self.navigate(match id {
+                Some(id) => Route::Person(id),
+                None => Route::NewPerson,
+            }),
+            Msg::GoToPersonsListPage => self.navigate(Route::PersonsList),
+            Msg::GoToImportPage => self.navigate(Route::ImportPersons),
+            Msg::SortPersons(sort) => self.persons_sort = sort,
+            Msg::GoToTrashPage => self.navigate(Route::Trash),
+            Msg::GoToUsersListPage => self.navigate(Route::UsersList),
+            Msg::GoToAuditLogPage => self.navigate(Route::AuditLog),
+            Msg::GoToBackupPage => self.navigate(Route::Backup),
+            // The route is shown once the session is restored
+            Msg::RouteChanged(_) if self.resuming != Loading::Idle => return false,
+            Msg::RouteChanged(route) => self.show(route),
+            // Rendering the page again makes it read the data again
+            Msg::Synced => {
+                if self.loading_data && self.db_conn.borrow().is_loaded() {
+                    self.loading_data = false;
+                    self.restore_session();
+                }
+
+                self.sync_count += 1;
+            }
+            Msg::DiscardUnreadable => {
+                if !DialogService::confirm(
+                    "The stored data will be lost for good and replaced by the data shown now, \
+                     continue?",
+                ) {
+                    return false;
+                }
+
+                if let Err(err) = self.db_conn.borrow_mut().discard_unreadable() {
+                    DialogService::alert(&err.to_string());
+                }
+
+                self.sync_count += 1;
+            }
+            Msg::Activity => {
+                if let Some(session) = &mut self.session {
+                    session.touch();
+                }
+
+                return self.update(Msg::CheckSession);
+            }
+            Msg::CheckSession => {
+                let session = match &self.session {
+                    Some(session) => session,
+                    None => return false,
+                };
+                let now = Utc::now();
+
+                if session.is_expired(now) {
+                    self.log_out();
+                    DialogService::alert("Your session has expired, please log in again");
+                } else {
+                    let left = session.deadline() - now;
+                    let warning = if left <= session::LOGOUT_WARNING {
+                        Some(left.num_seconds() + 1)
+                    } else {
+                        None
+                    };
+
+                    if warning == self.logout_warning {
+                        return false;
+                    }
+
+                    self.logout_warning = warning;
+                }
+            }
         }
 
         true
@@ -66,17 +478,19 @@ impl Component for AuthApp {
     }
 
     fn view(&self) -> Html {
-        let go_to_page = self.link.callback(|u: user::User| {
-            Msg::LoggedIn(u)
-        });
+        let go_to_page = self
+            .link
+            .callback(|logged_in: database::LoggedIn| Msg::LoggedIn(logged_in));
+
+        let go_to_persons_list_page = self.link.callback(|_| Msg::GoToPersonsListPage);
+
+        let go_to_one_person_page = self
+            .link
+            .callback(|id: Option<u32>| Msg::GoToOnePersonPage(id));
 
-        let go_to_persons_list_page = self.link.callback(|_| {
-            Msg::GoToPersonsListPage
-        });
+        let go_to_import_page = self.link.callback(|_| Msg::GoToImportPage);
 
-        let go_to_one_person_page = self.link.callback(|p: Option<person::Person>| {
-            Msg::GoToOnePersonPage(p)
-        });
+        let sort_persons = self.link.callback(Msg::SortPersons);
 
         html! {
             <div>
@@ -85,6 +499,9 @@ impl Component for AuthApp {
                         .current-user {
                             color: #0000C0;
                         }
+                        .session-warning, .storage-error {
+                            color: #C00000;
+                        }
                     "#}
                 </style>
                 <header>
@@ -108,42 +525,77 @@ impl Component for AuthApp {
                                 _ => html! {
                                     <span>
                                         {""}
-                                        <button onclick=&self.link.callback(|_| Msg::ChangeUser)>
-                                            {"Change User"}
+                                        <button onclick=&self.link.callback(|_| Msg::LogOut)>
+                                            {"Log Out"}
                                         </button>
+                                        { self.view_navigation() }
                                     </span>
                                 }
                             }
                         }
                     </p>
+                    { self.view_unreadable() }
+                    { self.view_logout_warning() }
                     <hr />
                 </header>
                 {
-                    match &self.page {
-                        Page::Login => html! {
-                            <Login
-                                user=self.current_user.clone()
-                                on_log_in=go_to_page.clone()
-                                db_conn=Some(self.db_conn.clone())
-                            />
-                        },
-                        Page::PersonsList => html! {
-                            <PersonsList
-                                can_write=self.can_write
-                                go_to_one_person_page=go_to_one_person_page.clone()
-                                db_conn=Some(self.db_conn.clone())
-                            />
-                        },
-                        Page::OnePerson(person) => html! {
-                            <Person
-                                can_write=self.can_write
-                                id=if let Some(person) = person { Some(person.id) } else { None }
-                                name=if let Some(person) = person { person.name.clone() } else { String::default() }
-                                go_to_persons_list=go_to_persons_list_page.clone()
-                                db_conn=Some(self.db_conn.clone())
-                            />
-                        },
-                        _ => html! { <div></div> }
+                    if self.resuming != Loading::Idle {
+                        view_loading(&self.resuming, self.link.callback(|_| Msg::RestoreSession))
+                    } else {
+                        match &self.page {
+                            Page::Login => html! {
+                                <Login
+                                    user=self.current_user.clone()
+                                    on_log_in=go_to_page.clone()
+                                    async_conn=Some(self.async_conn.clone())
+                                />
+                            },
+                            Page::PersonsList => html! {
+                                <PersonsList
+                                    current_user=self.current_user.clone()
+                                    go_to_one_person_page=go_to_one_person_page.clone()
+                                    go_to_import_page=go_to_import_page.clone()
+                                    sort=self.persons_sort
+                                    on_sort=sort_persons.clone()
+                                    sync_count=self.sync_count
+                                    async_conn=Some(self.async_conn.clone())
+                                    db_conn=Some(self.db_conn.clone())
+                                />
+                            },
+                            Page::OnePerson(id) => html! {
+                                <Person
+                                    current_user=self.current_user.clone()
+                                    person_id=*id
+                                    sync_count=self.sync_count
+                                    go_to_persons_list=go_to_persons_list_page.clone()
+                                    async_conn=Some(self.async_conn.clone())
+                                />
+                            },
+                            Page::ImportPersons => html! {
+                                <ImportPersons
+                                    go_to_persons_list=go_to_persons_list_page.clone()
+                                    db_conn=Some(self.db_conn.clone())
+                                />
+                            },
+                            Page::Trash => html! {
+                                <Trash
+                                    current_user=self.current_user.clone()
+                                    db_conn=Some(self.db_conn.clone())
+                                />
+                            },
+                            Page::UsersList => html! {
+                                <UsersList
+                                    current_user=self.current_user.clone()
+                                    db_conn=Some(self.db_conn.clone())
+                                />
+                            },
+                            Page::AuditLog => html! {
+                                <AuditLog db_conn=Some(self.db_conn.clone()) />
+                            },
+                            Page::BackupRestore => html! {
+                                <BackupRestore db_conn=Some(self.db_conn.clone()) />
+                            },
+                        }
                     }
                 }
             </div>
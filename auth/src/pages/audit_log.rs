@@ -0,0 +1,210 @@
+use chrono::{Local, NaiveDate};
+use yew::prelude::*;
+
+use crate::database::{AuditEntry, AuditQuery, DbConn};
+use crate::validation::DATE_FORMAT;
+
+pub struct AuditLog {
+    entries: Vec<AuditEntry>,
+    /// Users found in the whole log, offered by the user filter
+    usernames: Vec<String>,
+    username: String,
+    person_id: String,
+    from: String,
+    to: String,
+    db_conn: DbConn,
+    link: ComponentLink<Self>,
+}
+
+#[derive(Debug)]
+pub enum Msg {
+    ChangeUsername(String),
+    ChangePersonId(String),
+    ChangeFrom(String),
+    ChangeTo(String),
+    ClearFilters,
+}
+
+#[derive(Properties, Clone, Default)]
+pub struct Props {
+    pub db_conn: Option<DbConn>,
+}
+
+impl AuditLog {
+    /// Blank or invalid filters are ignored
+    fn query(&self) -> AuditQuery {
+        let date = |value: &str| NaiveDate::parse_from_str(value, DATE_FORMAT).ok();
+
+        AuditQuery {
+            username: Some(self.username.clone()).filter(|username| !username.is_empty()),
+            person_id: self.person_id.trim().parse().ok(),
+            from: date(&self.from),
+            to: date(&self.to),
+        }
+    }
+
+    fn reload(&mut self) {
+        let db = self.db_conn.borrow();
+        let mut usernames: Vec<String> = db
+            .query_audit(&AuditQuery::default())
+            .into_iter()
+            .map(|entry| entry.username)
+            .collect();
+
+        usernames.sort();
+        usernames.dedup();
+
+        self.entries = db.query_audit(&self.query());
+        self.usernames = usernames;
+    }
+
+    fn view_row(&self, entry: &AuditEntry) -> Html {
+        let timestamp = entry
+            .timestamp
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
+        let show = |value: &str| {
+            if value.is_empty() {
+                "(empty)".to_string()
+            } else {
+                value.to_string()
+            }
+        };
+
+        html! {
+            <tr>
+                <td>{timestamp}</td>
+                <td>{&entry.username}</td>
+                <td>{entry.operation.label()}</td>
+                <td>{entry.person_id}</td>
+                <td>
+                    {
+                        for entry.changes().into_iter().map(|(field, before, after)| html! {
+                            <div>
+                                {format!("{}: {} → {}", field.label(), show(&before), show(&after))}
+                            </div>
+                        })
+                    }
+                </td>
+            </tr>
+        }
+    }
+}
+
+impl Component for AuditLog {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let mut model = AuditLog {
+            entries: Vec::new(),
+            usernames: Vec::new(),
+            username: String::default(),
+            person_id: String::default(),
+            from: String::default(),
+            to: String::default(),
+            db_conn: props.db_conn.unwrap(),
+            link,
+        };
+
+        model.reload();
+
+        model
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::ChangeUsername(value) => self.username = value,
+            Msg::ChangePersonId(value) => self.person_id = value,
+            Msg::ChangeFrom(value) => self.from = value,
+            Msg::ChangeTo(value) => self.to = value,
+            Msg::ClearFilters => {
+                self.username.clear();
+                self.person_id.clear();
+                self.from.clear();
+                self.to.clear();
+            }
+        }
+
+        self.reload();
+
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.db_conn = props.db_conn.unwrap();
+        self.reload();
+
+        true
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <div>
+                <h3>{"Audit Log"}</h3>
+                <div>
+                    <label>{"User: "}</label>
+                    <select
+                        onchange=self.link.callback(|e: ChangeData| match e {
+                            ChangeData::Select(select) => Msg::ChangeUsername(select.value()),
+                            _ => Msg::ChangeUsername(String::default()),
+                        })
+                    >
+                        <option value="" selected=self.username.is_empty()>{"All"}</option>
+                        {
+                            for self.usernames.iter().map(|username| html! {
+                                <option value=username selected={*username == self.username}>
+                                    {username}
+                                </option>
+                            })
+                        }
+                    </select>
+                    <label>{" Person ID: "}</label>
+                    <input
+                        type="number"
+                        value=&self.person_id
+                        oninput=self.link.callback(|e: InputData| Msg::ChangePersonId(e.value))
+                    />
+                    <label>{" From: "}</label>
+                    <input
+                        type="date"
+                        value=&self.from
+                        oninput=self.link.callback(|e: InputData| Msg::ChangeFrom(e.value))
+                    />
+                    <label>{" To: "}</label>
+                    <input
+                        type="date"
+                        value=&self.to
+                        oninput=self.link.callback(|e: InputData| Msg::ChangeTo(e.value))
+                    />
+                    <button onclick=self.link.callback(|_| Msg::ClearFilters)>
+                        {"Clear Filters"}
+                    </button>
+                </div>
+                {
+                    if self.entries.is_empty() {
+                        html! {
+                            <p>{"No entries"}</p>
+                        }
+                    } else {
+                        html! {
+                            <table>
+                                <thead>
+                                    <th>{"Time"}</th>
+                                    <th>{"User"}</th>
+                                    <th>{"Operation"}</th>
+                                    <th>{"Person ID"}</th>
+                                    <th>{"Changes"}</th>
+                                </thead>
+                                <tbody>
+                                    { for self.entries.iter().map(|entry| self.view_row(entry)) }
+                                </tbody>
+                            </table>
+                        }
+                    }
+                }
+            </div>
+        }
+    }
+}
@@ -0,0 +1,317 @@
+use chrono::Local;
+use yew::prelude::*;
+use yew::services::reader::{FileData, ReaderService, ReaderTask};
+use yew::services::DialogService;
+
+use crate::database::{Backup, BackupDiff, DbConn, DbResult, RestoreMode};
+use crate::download;
+use crate::person::{Person, PersonField};
+
+/// Downloads the whole `Database` as a JSON backup and restores one,
+/// showing what the restore would change before doing it
+pub struct BackupRestore {
+    backup: Option<Backup>,
+    mode: RestoreMode,
+    /// Outcome of restoring `backup` with `mode`, without restoring it
+    diff: Option<DbResult<BackupDiff>>,
+    _reader_task: Option<ReaderTask>,
+    db_conn: DbConn,
+    link: ComponentLink<Self>,
+}
+
+pub enum Msg {
+    Download,
+    PickFile(ChangeData),
+    FileLoaded(FileData),
+    ChangeMode(RestoreMode),
+    Restore,
+}
+
+#[derive(Properties, Clone, Default)]
+pub struct Props {
+    pub db_conn: Option<DbConn>,
+}
+
+impl BackupRestore {
+    fn refresh_diff(&mut self) {
+        self.diff = self
+            .backup
+            .as_ref()
+            .map(|backup| self.db_conn.borrow().preview_restore(backup, self.mode));
+    }
+
+    fn download(&self) -> Result<(), String> {
+        let json = self
+            .db_conn
+            .borrow()
+            .backup()
+            .and_then(|backup| backup.to_json())
+            .map_err(|err| err.to_string())?;
+        let file_name = format!("auth-backup-{}.json", Local::now().format("%Y-%m-%d"));
+
+        download::download(&file_name, "application/json", &json).map_err(|err| {
+            err.as_string()
+                .unwrap_or_else(|| "Unable to download".to_string())
+        })
+    }
+
+    fn view_mode(&self, mode: RestoreMode, label: &str) -> Html {
+        html! {
+            <label>
+                <input
+                    type="radio"
+                    name="restore-mode"
+                    checked=self.mode == mode
+                    onclick=self.link.callback(move |_| Msg::ChangeMode(mode))
+                />
+                {label}
+            </label>
+        }
+    }
+
+    fn view_person(&self, change: &str, person: &Person, details: String) -> Html {
+        html! {
+            <tr>
+                <td>{change}</td>
+                <td>{person.id}</td>
+                <td>{&person.name}</td>
+                <td>{details}</td>
+            </tr>
+        }
+    }
+
+    fn view_users(&self, title: &str, usernames: &[String]) -> Html {
+        if usernames.is_empty() {
+            return html! {};
+        }
+
+        html! {
+            <p>{format!("{}: {}", title, usernames.join(", "))}</p>
+        }
+    }
+
+    fn view_diff(&self, diff: &BackupDiff) -> Html {
+        if diff.is_empty() {
+            return html! {
+                <p>{"Restoring the backup doesn't change anything"}</p>
+            };
+        }
+
+        let changed_fields = |stored: &Person, person: &Person| {
+            PersonField::ALL
+                .iter()
+                .filter(|field| stored.field_value(**field) != person.field_value(**field))
+                .map(|field| field.label())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        };
+
+        html! {
+            <>
+                <p>
+                    {format!(
+                        "Persons: {} added, {} changed, {} removed",
+                        diff.added_persons.len(),
+                        diff.changed_persons.len(),
+                        diff.removed_persons.len(),
+                    )}
+                </p>
+                <table>
+                    <thead>
+                        <th>{"Change"}</th>
+                        <th>{"ID"}</th>
+                        <th>{"Name"}</th>
+                        <th>{"Changed Fields"}</th>
+                    </thead>
+                    <tbody>
+                        { for diff.added_persons.iter().map(|person| {
+                            self.view_person("Added", person, String::default())
+                        }) }
+                        { for diff.changed_persons.iter().map(|(stored, person)| {
+                            self.view_person("Changed", person, changed_fields(stored, person))
+                        }) }
+                        { for diff.removed_persons.iter().map(|person| {
+                            self.view_person("Removed", person, String::default())
+                        }) }
+                    </tbody>
+                </table>
+                { self.view_users("Users added", &diff.added_users) }
+                { self.view_users("Users changed", &diff.changed_users) }
+                { self.view_users("Users removed", &diff.removed_users) }
+            </>
+        }
+    }
+
+    fn view_restore(&self) -> Html {
+        let backup = match &self.backup {
+            Some(backup) => backup,
+            None => return html! {},
+        };
+        let created_at = backup
+            .created_at
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
+        let can_restore = matches!(self.diff, Some(Ok(_)));
+
+        html! {
+            <>
+                <p>
+                    {format!(
+                        "Backup made by {} on {}, with {} persons and {} users",
+                        backup.created_by,
+                        created_at,
+                        backup.persons.len(),
+                        backup.users.len(),
+                    )}
+                </p>
+                <div>
+                    { self.view_mode(RestoreMode::Merge, "Merge into the current data") }
+                    { self.view_mode(RestoreMode::Replace, "Replace the current data") }
+                </div>
+                <h4>{"Changes"}</h4>
+                {
+                    match &self.diff {
+                        Some(Ok(diff)) => self.view_diff(diff),
+                        Some(Err(err)) => html! { <p class="error">{err.to_string()}</p> },
+                        None => html! {},
+                    }
+                }
+                <button disabled=!can_restore onclick=self.link.callback(|_| Msg::Restore)>
+                    {"Restore"}
+                </button>
+            </>
+        }
+    }
+}
+
+impl Component for BackupRestore {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        BackupRestore {
+            backup: None,
+            mode: RestoreMode::Merge,
+            diff: None,
+            _reader_task: None,
+            db_conn: props.db_conn.unwrap(),
+            link,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Download => {
+                if let Err(err) = self.download() {
+                    DialogService::alert(&err);
+                }
+
+                return false;
+            }
+            Msg::PickFile(ChangeData::Files(files)) => {
+                if let Some(file) = files.get(0) {
+                    let callback = self.link.callback(Msg::FileLoaded);
+
+                    match ReaderService::new().read_file(file, callback) {
+                        Ok(task) => self._reader_task = Some(task),
+                        Err(err) => {
+                            DialogService::alert(&format!("Unable to read the file: {}", err))
+                        }
+                    }
+                }
+
+                return false;
+            }
+            Msg::PickFile(_) => return false,
+            Msg::FileLoaded(data) => {
+                self._reader_task = None;
+
+                let backup = String::from_utf8(data.content)
+                    .map_err(|_| "The file is not UTF-8 text".to_string())
+                    .and_then(|json| Backup::from_json(&json).map_err(|err| err.to_string()));
+
+                match backup {
+                    Ok(backup) => self.backup = Some(backup),
+                    Err(err) => {
+                        DialogService::alert(&format!("Unable to read the backup: {}", err));
+                        self.backup = None;
+                    }
+                }
+
+                self.refresh_diff();
+            }
+            Msg::ChangeMode(mode) => {
+                self.mode = mode;
+                self.refresh_diff();
+            }
+            Msg::Restore => {
+                let backup = match &self.backup {
+                    Some(backup) => backup,
+                    None => return false,
+                };
+                let question = match self.mode {
+                    RestoreMode::Merge => "Are you sure you want to merge the backup?",
+                    RestoreMode::Replace => {
+                        "Are you sure you want to replace all the data with the backup? \
+                         Anything missing from it will be lost"
+                    }
+                };
+
+                if !DialogService::confirm(question) {
+                    return false;
+                }
+
+                let result = self.db_conn.borrow_mut().restore_backup(backup, self.mode);
+
+                match result {
+                    Ok(_) => {
+                        DialogService::alert("The backup was restored");
+                        self.backup = None;
+                    }
+                    Err(err) => DialogService::alert(&err.to_string()),
+                }
+
+                self.refresh_diff();
+            }
+        }
+
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.db_conn = props.db_conn.unwrap();
+        self.refresh_diff();
+
+        true
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <div>
+                <style>
+                    {r#"
+                        .error {
+                            color: #C00000;
+                        }
+                    "#}
+                </style>
+                <h3>{"Backup"}</h3>
+                <p>{"Every person, deleted person and user, users only with their password hashes"}</p>
+                <button onclick=self.link.callback(|_| Msg::Download)>
+                    {"Download Backup"}
+                </button>
+                <h3>{"Restore"}</h3>
+                <div>
+                    <label>{"Backup file: "}</label>
+                    <input
+                        type="file"
+                        accept=".json,application/json"
+                        onchange=self.link.callback(Msg::PickFile)
+                    />
+                </div>
+                { self.view_restore() }
+            </div>
+        }
+    }
+}
@@ -0,0 +1,348 @@
+use yew::prelude::*;
+use yew::services::reader::{FileData, ReaderService, ReaderTask};
+use yew::services::DialogService;
+
+use crate::database::DbConn;
+use crate::person::PersonField;
+use crate::person_csv::{self, ColumnMapping, CsvColumn, CsvTable, PreviewRow, RowAction};
+
+/// Imports persons from a CSV file in three steps: picking the file,
+/// mapping its columns and checking a preview before the single commit
+pub struct ImportPersons {
+    file_name: Option<String>,
+    table: CsvTable,
+    mapping: ColumnMapping,
+    preview: Vec<PreviewRow>,
+    _reader_task: Option<ReaderTask>,
+    go_to_persons_list: Option<Callback<()>>,
+    db_conn: DbConn,
+    link: ComponentLink<Self>,
+}
+
+pub enum Msg {
+    PickFile(ChangeData),
+    FileLoaded(FileData),
+    MapColumn(CsvColumn, String),
+    Import,
+    Cancel,
+}
+
+#[derive(Properties, Clone, Default)]
+pub struct Props {
+    pub go_to_persons_list: Option<Callback<()>>,
+    pub db_conn: Option<DbConn>,
+}
+
+impl ImportPersons {
+    fn refresh_preview(&mut self) {
+        let db = self.db_conn.borrow();
+
+        self.preview =
+            person_csv::preview(&self.table, &self.mapping, |id| db.get_person_by_id(id));
+    }
+
+    fn count(&self, matches: fn(&PreviewRow) -> bool) -> usize {
+        self.preview.iter().filter(|row| matches(row)).count()
+    }
+
+    fn view_mapping(&self, column: CsvColumn) -> Html {
+        let mapped = self.mapping.get(&column).copied();
+
+        html! {
+            <tr>
+                <td>{column.label()}</td>
+                <td>
+                    <select
+                        onchange=self.link.callback(move |e: ChangeData| match e {
+                            ChangeData::Select(select) => Msg::MapColumn(column, select.value()),
+                            _ => Msg::MapColumn(column, String::default()),
+                        })
+                    >
+                        <option value="" selected=mapped.is_none()>{"(Leave blank)"}</option>
+                        {
+                            for self.table.headers.iter().enumerate().map(|(index, header)| html! {
+                                <option value=index.to_string() selected={mapped == Some(index)}>
+                                    {header}
+                                </option>
+                            })
+                        }
+                    </select>
+                </td>
+            </tr>
+        }
+    }
+
+    fn view_preview_row(&self, row: &PreviewRow) -> Html {
+        let changed = row.changed_fields();
+        let (action, notes) = match (&row.action, row.unknown_id) {
+            (RowAction::Insert, Some(id)) => (
+                "Insert",
+                format!("No person has ID {}, a new ID is assigned", id),
+            ),
+            (RowAction::Insert, None) => ("Insert", String::default()),
+            (RowAction::Update, _) if changed.is_empty() => ("Update", "No changes".to_string()),
+            (RowAction::Update, _) => {
+                let labels: Vec<&str> = changed.iter().map(|field| field.label()).collect();
+
+                ("Update", format!("Changes {}", labels.join(", ")))
+            }
+            (RowAction::Reject(errors), _) => ("Reject", errors.join(", ")),
+        };
+        let id = match row.action {
+            RowAction::Update => row.person.id.to_string(),
+            _ => String::default(),
+        };
+        let rejected = matches!(row.action, RowAction::Reject(_));
+
+        html! {
+            <tr class=if rejected { "rejected" } else { "" }>
+                <td>{row.line}</td>
+                <td>{action}</td>
+                <td>{id}</td>
+                { for PersonField::ALL.iter().map(|field| self.view_field(row, *field, &changed)) }
+                <td>{notes}</td>
+            </tr>
+        }
+    }
+
+    /// Value the row imports for `field`, along with the stored value it
+    /// replaces when it updates a person
+    fn view_field(&self, row: &PreviewRow, field: PersonField, changed: &[PersonField]) -> Html {
+        let value = row.person.field_value(field);
+
+        match &row.stored {
+            Some(stored) if row.action == RowAction::Update && changed.contains(&field) => html! {
+                <td class="changed">
+                    <del>{stored.field_value(field)}</del>
+                    {" → "}
+                    {value}
+                </td>
+            },
+            Some(_) if row.action == RowAction::Update => html! {
+                <td class="unchanged">{value}</td>
+            },
+            _ => html! {
+                <td>{value}</td>
+            },
+        }
+    }
+
+    fn view_preview(&self) -> Html {
+        if self.preview.is_empty() {
+            return html! {
+                <p>{"The file has no rows to import"}</p>
+            };
+        }
+
+        let inserted = self.count(|row| row.action == RowAction::Insert);
+        let unknown = self.count(|row| row.action == RowAction::Insert && row.unknown_id.is_some());
+
+        html! {
+            <>
+                <p>
+                    {format!(
+                        "{} to insert ({} with an unknown ID), {} to update, {} rejected",
+                        inserted,
+                        unknown,
+                        self.count(|row| row.action == RowAction::Update),
+                        self.count(|row| matches!(row.action, RowAction::Reject(_))),
+                    )}
+                </p>
+                <table>
+                    <thead>
+                        <th>{"Line"}</th>
+                        <th>{"Action"}</th>
+                        <th>{"ID"}</th>
+                        { for PersonField::ALL.iter().map(|field| html! {
+                            <th>{field.label()}</th>
+                        }) }
+                        <th>{"Notes"}</th>
+                    </thead>
+                    <tbody>
+                        { for self.preview.iter().map(|row| self.view_preview_row(row)) }
+                    </tbody>
+                </table>
+            </>
+        }
+    }
+}
+
+impl Component for ImportPersons {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        ImportPersons {
+            file_name: None,
+            table: CsvTable::default(),
+            mapping: ColumnMapping::new(),
+            preview: Vec::new(),
+            _reader_task: None,
+            go_to_persons_list: props.go_to_persons_list,
+            db_conn: props.db_conn.unwrap(),
+            link,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::PickFile(ChangeData::Files(files)) => {
+                if let Some(file) = files.get(0) {
+                    let callback = self.link.callback(Msg::FileLoaded);
+
+                    match ReaderService::new().read_file(file, callback) {
+                        Ok(task) => self._reader_task = Some(task),
+                        Err(err) => {
+                            DialogService::alert(&format!("Unable to read the file: {}", err))
+                        }
+                    }
+                }
+
+                return false;
+            }
+            Msg::PickFile(_) => return false,
+            Msg::FileLoaded(data) => {
+                self._reader_task = None;
+
+                let parsed = String::from_utf8(data.content)
+                    .map_err(|_| "The file is not UTF-8 text".to_string())
+                    .and_then(|text| person_csv::parse(&text));
+
+                match parsed {
+                    Ok(table) => {
+                        self.file_name = Some(data.name);
+                        self.mapping = person_csv::guess_mapping(&table.headers);
+                        self.table = table;
+                        self.refresh_preview();
+                    }
+                    Err(err) => DialogService::alert(&format!("Unable to read the file: {}", err)),
+                }
+            }
+            Msg::MapColumn(column, value) => {
+                match value.parse::<usize>() {
+                    Ok(index) => self.mapping.insert(column, index),
+                    Err(_) => self.mapping.remove(&column),
+                };
+
+                self.refresh_preview();
+            }
+            Msg::Import => {
+                let mut inserts = Vec::new();
+                let mut updates = Vec::new();
+
+                for row in &self.preview {
+                    match row.action {
+                        RowAction::Insert => inserts.push(row.person.clone()),
+                        RowAction::Update => updates.push(row.person.clone()),
+                        RowAction::Reject(_) => {}
+                    }
+                }
+
+                let result = self.db_conn.borrow_mut().import_persons(inserts, updates);
+
+                match result {
+                    Ok((inserted, updated)) => {
+                        DialogService::alert(&format!(
+                            "{} entries inserted and {} updated",
+                            inserted, updated
+                        ));
+
+                        if let Some(callback) = &self.go_to_persons_list {
+                            callback.emit(());
+                        }
+                    }
+                    Err(err) => {
+                        DialogService::alert(&err.to_string());
+                        self.refresh_preview();
+                    }
+                }
+            }
+            Msg::Cancel => {
+                if let Some(callback) = &self.go_to_persons_list {
+                    callback.emit(());
+                }
+            }
+        }
+
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.go_to_persons_list = props.go_to_persons_list;
+        self.db_conn = props.db_conn.unwrap();
+        self.refresh_preview();
+
+        true
+    }
+
+    fn view(&self) -> Html {
+        let importable = self.preview.iter().any(|row| match row.action {
+            RowAction::Insert | RowAction::Update => true,
+            RowAction::Reject(_) => false,
+        });
+
+        html! {
+            <div>
+                <style>
+                    {r#"
+                        tr.rejected {
+                            color: #C00000;
+                        }
+                        td.changed {
+                            font-weight: bold;
+                        }
+                        td.changed del {
+                            font-weight: normal;
+                            color: #808080;
+                        }
+                        td.unchanged {
+                            color: #808080;
+                        }
+                    "#}
+                </style>
+                <h3>{"Import Persons"}</h3>
+                <div>
+                    <label>{"CSV file: "}</label>
+                    <input
+                        type="file"
+                        accept=".csv,text/csv"
+                        onchange=self.link.callback(Msg::PickFile)
+                    />
+                </div>
+                {
+                    if self.file_name.is_none() {
+                        html! {}
+                    } else {
+                        html! {
+                            <>
+                                <h4>{"Columns"}</h4>
+                                <table>
+                                    <thead>
+                                        <th>{"Field"}</th>
+                                        <th>{"Column of the File"}</th>
+                                    </thead>
+                                    <tbody>
+                                        { for CsvColumn::all().into_iter().map(|column| self.view_mapping(column)) }
+                                    </tbody>
+                                </table>
+                                <h4>{"Preview"}</h4>
+                                { self.view_preview() }
+                            </>
+                        }
+                    }
+                }
+                <div>
+                    <button
+                        disabled=!importable
+                        onclick=self.link.callback(|_| Msg::Import)
+                    >
+                        {"Import"}
+                    </button>
+                    <button onclick=self.link.callback(|_| Msg::Cancel)>
+                        {"Cancel"}
+                    </button>
+                </div>
+            </div>
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use yew::prelude::*;
+
+use crate::database::DbError;
+
+/// State of the request a page waits for
+#[derive(Debug, Clone, PartialEq)]
+pub enum Loading {
+    Idle,
+    Busy,
+    /// The request failed before reaching the data, it can be retried
+    Failed(String),
+}
+
+impl Loading {
+    pub fn is_busy(&self) -> bool {
+        *self == Loading::Busy
+    }
+
+    /// `Failed` for the errors a retry may fix, `None` for the others
+    pub fn from_error(err: &DbError) -> Option<Self> {
+        match err {
+            DbError::Storage(_) => Some(Loading::Failed(err.to_string())),
+            _ => None,
+        }
+    }
+}
+
+/// A spinner while busy, or the error with a button to retry once failed
+pub fn view_loading(loading: &Loading, on_retry: Callback<MouseEvent>) -> Html {
+    match loading {
+        Loading::Idle => html! {},
+        Loading::Busy => html! {
+            <div class="loading">
+                <style>
+                    {r#"
+                        .spinner {
+                            display: inline-block;
+                            width: 12px;
+                            height: 12px;
+                            margin-right: 8px;
+                            border: 2px solid #C0C0C0;
+                            border-top-color: #0000C0;
+                            border-radius: 50%;
+                            animation: spin 0.8s linear infinite;
+                        }
+                        @keyframes spin {
+                            to {
+                                transform: rotate(360deg);
+                            }
+                        }
+                    "#}
+                </style>
+                <span class="spinner" />
+                {"Loading..."}
+            </div>
+        },
+        Loading::Failed(message) => html! {
+            <p class="request-error">
+                <style>
+                    {r#"
+                        .request-error {
+                            color: #C00000;
+                        }
+                    "#}
+                </style>
+                {format!("{} ", message)}
+                <button onclick=on_retry>{"Retry"}</button>
+            </p>
+        },
+    }
+}
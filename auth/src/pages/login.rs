@@ -1,15 +1,18 @@
-use crate::database::DbConn;
-use crate::user::User;
+use crate::database::{AsyncConn, DbResult, LoggedIn, Pending};
+use crate::pages::{view_loading, Loading};
+use crate::user::AuthenticatedUser;
 use yew::prelude::*;
 use yew::services::DialogService;
 use yew::{html, Callback};
 
 pub struct Login {
-    user: Option<User>,
-    on_log_in: Option<Callback<User>>,
-    db_conn: DbConn,
+    user: Option<AuthenticatedUser>,
+    on_log_in: Option<Callback<LoggedIn>>,
+    async_conn: AsyncConn,
     link: ComponentLink<Self>,
     state: State,
+    /// Logging in, dropping it cancels the login
+    request: Option<Pending>,
 }
 
 #[derive(Debug)]
@@ -17,18 +20,20 @@ pub enum Msg {
     UsernameChange(String),
     PasswordChange(String),
     OnLogin,
+    LoggedIn(DbResult<LoggedIn>),
 }
 
 #[derive(Clone, Properties)]
 pub struct Props {
-    pub user: Option<User>,
-    pub on_log_in: Option<Callback<User>>,
-    pub db_conn: Option<DbConn>,
+    pub user: Option<AuthenticatedUser>,
+    pub on_log_in: Option<Callback<LoggedIn>>,
+    pub async_conn: Option<AsyncConn>,
 }
 
 struct State {
-  username_field: String,
-  password_field: String,
+    username_field: String,
+    password_field: String,
+    loading: Loading,
 }
 
 impl Default for Props {
@@ -36,18 +41,19 @@ impl Default for Props {
         Self {
             user: None,
             on_log_in: None,
-            db_conn: None,
+            async_conn: None,
         }
     }
 }
 
 impl Default for State {
-  fn default() -> Self {
-      Self {
-        username_field: String::default(),
-        password_field: String::default(),
-      }
-  }
+    fn default() -> Self {
+        Self {
+            username_field: String::default(),
+            password_field: String::default(),
+            loading: Loading::Idle,
+        }
+    }
 }
 
 impl Component for Login {
@@ -58,41 +64,55 @@ impl Component for Login {
         Self {
             user: props.user,
             on_log_in: props.on_log_in,
-            db_conn: props.db_conn.unwrap(),
+            async_conn: props.async_conn.unwrap(),
             state: State::default(),
+            request: None,
             link,
         }
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
-      match msg {
-        Msg::UsernameChange(new_username) => self.state.username_field = new_username,
-        Msg::PasswordChange(new_password) => self.state.password_field = new_password,
-        Msg::OnLogin => {
-          if let Some(user) = self
-            .db_conn
-            .borrow()
-            .get_user_by_username(&self.state.username_field) {
-              if user.password == self.state.password_field {
-                if let Some(ref got_to_page) = self.on_log_in {
-                  got_to_page.emit(user.clone());
+        match msg {
+            Msg::UsernameChange(new_username) => self.state.username_field = new_username,
+            Msg::PasswordChange(new_password) => self.state.password_field = new_password,
+            Msg::OnLogin => {
+                self.state.loading = Loading::Busy;
+                self.request = Some(self.async_conn.log_in(
+                    &self.state.username_field,
+                    &self.state.password_field,
+                    self.link.callback(Msg::LoggedIn),
+                ));
+            }
+            Msg::LoggedIn(result) => {
+                self.request = None;
+                self.state.loading = Loading::Idle;
+
+                match result {
+                    Ok(logged_in) => {
+                        self.state.password_field.clear();
+
+                        if let Some(ref got_to_page) = self.on_log_in {
+                            got_to_page.emit(logged_in);
+                        }
+                    }
+                    Err(err) => match Loading::from_error(&err) {
+                        Some(loading) => self.state.loading = loading,
+                        None => {
+                            self.state.password_field.clear();
+                            DialogService::alert(&err.to_string());
+                        }
+                    },
                 }
-              } else {
-                DialogService::alert("Invalid password!");
-              }
-            } else {
-              DialogService::alert("User not found");
             }
         }
-      }
 
-      true
+        true
     }
 
     fn change(&mut self, props: Self::Properties) -> ShouldRender {
         self.user = props.user;
         self.on_log_in = props.on_log_in;
-        self.db_conn = props.db_conn.unwrap();
+        self.async_conn = props.async_conn.unwrap();
 
         true
     }
@@ -117,9 +137,11 @@ impl Component for Login {
                     />
                 </div>
                 <button
+                    disabled=self.state.loading.is_busy()
                     onclick=self.link.callback(|_| Msg::OnLogin)>
                     { "Log in" }
                 </button>
+                { view_loading(&self.state.loading, self.link.callback(|_| Msg::OnLogin)) }
             </div>
         }
     }
@@ -1,7 +1,19 @@
+mod audit_log;
+mod backup_restore;
+mod import_persons;
+mod loading;
 mod login;
 mod person;
 mod persons_list;
+mod trash;
+mod users_list;
 
+pub use audit_log::*;
+pub use backup_restore::*;
+pub use import_persons::*;
+pub use loading::*;
 pub use login::*;
 pub use person::*;
 pub use persons_list::*;
+pub use trash::*;
+pub use users_list::*;
@@ -1,44 +1,316 @@
-use crate::database::DbConn;
+use crate::database::{AsyncConn, DbError, DbResult, Pending};
+use crate::pages::{view_loading, Loading};
+use crate::person::{Person as PersonRecord, PersonField};
+use crate::user::{AuthenticatedUser, DbPrivilege};
 
+use std::collections::{HashMap, HashSet};
 use yew::prelude::*;
+use yew::services::DialogService;
 
 pub struct Person {
     id: Option<u32>,
-    can_write: bool,
+    current_user: Option<AuthenticatedUser>,
     go_to_persons_list: Option<Callback<()>>,
-    db_conn: DbConn,
+    async_conn: AsyncConn,
     state: State,
+    /// Loading or saving the person, dropping it cancels the request
+    request: Option<Pending>,
+    /// Checking the person is still there after another tab changed the data
+    check_request: Option<Pending>,
+    sync_count: usize,
     link: ComponentLink<Self>,
 }
 
 struct State {
     is_inserting: bool,
-    name_value: String,
+    /// The person as it was loaded, the form edits this version of it
+    stored: Option<PersonRecord>,
+    /// The person as someone else saved it meanwhile, the user chooses how
+    /// to go on with the edit
+    conflict: Option<PersonRecord>,
+    /// Whether another tab deleted the person being edited
+    is_deleted: bool,
+    loading: Loading,
+    form: PersonForm,
+}
+
+/// Ways to go on with an edit of a person someone else changed meanwhile
+#[derive(Debug, Clone, Copy)]
+pub enum Resolution {
+    /// Saves the edit as it is, losing the other changes
+    Overwrite,
+    /// Keeps the fields the user changed and takes the others as stored
+    Merge,
+    /// Drops the edit and shows the stored person
+    Discard,
+}
+
+/// Raw text of every field as typed by the user, along with the
+/// validation errors of the fields the user already touched
+#[derive(Debug, Clone, Default)]
+struct PersonForm {
+    values: HashMap<PersonField, String>,
+    errors: HashMap<PersonField, String>,
+    touched: HashSet<PersonField>,
+}
+
+impl PersonForm {
+    fn from_person(person: &PersonRecord) -> Self {
+        Self {
+            values: PersonField::ALL
+                .iter()
+                .map(|field| (*field, person.field_value(*field)))
+                .collect(),
+            ..Self::default()
+        }
+    }
+
+    fn value(&self, field: PersonField) -> &str {
+        self.values
+            .get(&field)
+            .map(String::as_str)
+            .unwrap_or_default()
+    }
+
+    fn set_value(&mut self, field: PersonField, value: String) {
+        self.values.insert(field, value);
+        self.touched.insert(field);
+        self.validate();
+    }
+
+    /// Shown errors only cover touched fields, but the form is only valid
+    /// when every field passes its rules
+    fn validate(&mut self) -> bool {
+        let mut is_valid = true;
+
+        self.errors.clear();
+
+        for field in PersonField::ALL.iter() {
+            if let Err(message) = field.validate(self.value(*field)) {
+                is_valid = false;
+
+                if self.touched.contains(field) {
+                    self.errors.insert(*field, message);
+                }
+            }
+        }
+
+        is_valid
+    }
+
+    /// Marks every field as touched so all the errors are shown
+    fn validate_all(&mut self) -> bool {
+        self.touched.extend(PersonField::ALL.iter());
+        self.validate()
+    }
+
+    /// Takes the values of `theirs` for the fields the user didn't change
+    /// from `base`
+    fn merge(&mut self, base: &PersonRecord, theirs: &PersonRecord) {
+        for field in PersonField::ALL.iter() {
+            if self.value(*field) == base.field_value(*field) {
+                self.values.insert(*field, theirs.field_value(*field));
+            }
+        }
+
+        self.validate_all();
+    }
+
+    /// Builds the record to store, blank optional fields become `None`
+    fn to_person(&self, id: u32, version: u32) -> PersonRecord {
+        PersonRecord {
+            version,
+            ..PersonRecord::from_values(id, |field| self.value(field).to_string())
+        }
+    }
+}
+
+fn input_type(field: PersonField) -> &'static str {
+    match field {
+        PersonField::Email => "email",
+        PersonField::Phone => "tel",
+        PersonField::BirthDate => "date",
+        _ => "text",
+    }
 }
 
 pub enum Msg {
-    ChangeName(String),
+    ChangeField(PersonField, String),
+    Loaded(DbResult<PersonRecord>),
     Save,
+    Saved(DbResult<()>),
+    Checked(DbResult<PersonRecord>),
+    Resolve(Resolution),
+    Retry,
     Cancel,
 }
 
-#[derive(Debug, Clone, Properties)]
+#[derive(Clone, Properties)]
 pub struct PersonProps {
-    pub id: Option<u32>,
-    pub name: String,
-    pub can_write: bool,
+    /// The person to edit, `None` to insert a new one
+    pub person_id: Option<u32>,
+    pub current_user: Option<AuthenticatedUser>,
     pub go_to_persons_list: Option<Callback<()>>,
-    pub db_conn: Option<DbConn>,
+    pub async_conn: Option<AsyncConn>,
+    /// Number of times other tabs changed the data, the person is checked
+    /// again every time it grows
+    pub sync_count: usize,
 }
 
 impl Default for PersonProps {
     fn default() -> Self {
         Self {
-            id: None,
-            name: String::default(),
-            can_write: false,
+            person_id: None,
+            current_user: None,
             go_to_persons_list: None,
-            db_conn: None,
+            async_conn: None,
+            sync_count: 0,
+        }
+    }
+}
+
+impl Person {
+    /// Loads the person to edit, an empty form is shown to insert one
+    fn load(&mut self) {
+        self.state.is_inserting = self.id.is_none();
+        self.state.stored = None;
+        self.state.conflict = None;
+        self.state.is_deleted = false;
+        self.state.form = PersonForm::default();
+
+        match self.id {
+            Some(id) => {
+                self.state.loading = Loading::Busy;
+                self.request = Some(
+                    self.async_conn
+                        .get_person(id, self.link.callback(Msg::Loaded)),
+                );
+            }
+            None => {
+                self.state.loading = Loading::Idle;
+                self.request = None;
+            }
+        }
+    }
+
+    fn save(&mut self) {
+        let version = self
+            .state
+            .stored
+            .as_ref()
+            .map_or(0, |stored| stored.version);
+        let person = self
+            .state
+            .form
+            .to_person(self.id.unwrap_or_default(), version);
+        let callback = self.link.callback(Msg::Saved);
+
+        self.state.loading = Loading::Busy;
+        self.request = Some(if self.state.is_inserting {
+            self.async_conn.insert(
+                person,
+                callback.reform(|result: DbResult<u32>| result.map(|_| ())),
+            )
+        } else {
+            self.async_conn.update(person, callback)
+        });
+    }
+
+    fn go_to_persons_list(&self) {
+        if let Some(ref go_to_page) = self.go_to_persons_list {
+            go_to_page.emit(());
+        }
+    }
+
+    /// Whether the user may save the form, the `Database` checks it again
+    fn can_save(&self) -> bool {
+        let privilege = if self.state.is_inserting {
+            DbPrivilege::CanCreate
+        } else {
+            DbPrivilege::CanUpdate
+        };
+
+        self.current_user
+            .as_ref()
+            .is_some_and(|user| user.has_privilege(privilege))
+    }
+
+    fn view_field(&self, field: PersonField) -> Html {
+        html! {
+            <div>
+                <label>{format!("{}: ", field.label())}</label>
+                <input
+                    type=input_type(field)
+                    value=self.state.form.value(field)
+                    disabled=!self.can_save() || self.state.loading.is_busy()
+                    oninput=self.link.callback(move |e: InputData| Msg::ChangeField(field, e.value))
+                />
+                {
+                    if let Some(message) = self.state.form.errors.get(&field) {
+                        html! { <span class="field-error">{message}</span> }
+                    } else {
+                        html! {}
+                    }
+                }
+            </div>
+        }
+    }
+
+    /// The stored person next to the edit of the user, with the ways to
+    /// go on with the edit
+    fn view_conflict(&self) -> Html {
+        let conflict = match &self.state.conflict {
+            Some(conflict) => conflict,
+            None => return html! {},
+        };
+        let on_resolve =
+            |resolution: Resolution| self.link.callback(move |_| Msg::Resolve(resolution));
+
+        html! {
+            <div class="conflict">
+                <p>{"Someone else changed this person since you opened it"}</p>
+                <table>
+                    <thead>
+                        <th>{"Field"}</th>
+                        <th>{"Stored"}</th>
+                        <th>{"Your Edit"}</th>
+                    </thead>
+                    <tbody>
+                        {
+                            for PersonField::ALL.iter().map(|field| {
+                                let stored = conflict.field_value(*field);
+                                let edited = self.state.form.value(*field);
+
+                                html! {
+                                    <tr class=if stored == edited { "" } else { "differs" }>
+                                        <td>{field.label()}</td>
+                                        <td>{&stored}</td>
+                                        <td>{edited}</td>
+                                    </tr>
+                                }
+                            })
+                        }
+                    </tbody>
+                </table>
+                <button
+                    title="Saves your edit, losing the other changes"
+                    onclick=on_resolve(Resolution::Overwrite)
+                >
+                    {"Overwrite"}
+                </button>
+                <button
+                    title="Keeps the fields you changed and takes the others as stored, to review before saving"
+                    onclick=on_resolve(Resolution::Merge)
+                >
+                    {"Merge"}
+                </button>
+                <button
+                    title="Drops your edit and shows the stored person"
+                    onclick=on_resolve(Resolution::Discard)
+                >
+                    {"Discard"}
+                </button>
+            </div>
         }
     }
 }
@@ -48,52 +320,140 @@ impl Component for Person {
     type Properties = PersonProps;
 
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
-        Self {
-            id: props.id,
-            can_write: props.can_write,
+        let mut person = Self {
+            id: props.person_id,
+            current_user: props.current_user,
             go_to_persons_list: props.go_to_persons_list,
-            db_conn: props.db_conn.unwrap(),
+            async_conn: props.async_conn.unwrap(),
+            request: None,
+            check_request: None,
+            sync_count: props.sync_count,
             link,
             state: State {
-                is_inserting: props.id.is_none(),
-                name_value: props.name,
+                is_inserting: props.person_id.is_none(),
+                stored: None,
+                conflict: None,
+                is_deleted: false,
+                loading: Loading::Idle,
+                form: PersonForm::default(),
             },
-        }
+        };
+
+        person.load();
+
+        person
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         match msg {
-            Msg::ChangeName(value) => self.state.name_value = value,
+            Msg::ChangeField(field, value) => self.state.form.set_value(field, value),
+            Msg::Loaded(result) => {
+                self.request = None;
+
+                match result {
+                    Ok(person) => {
+                        self.state.form = PersonForm::from_person(&person);
+                        self.state.stored = Some(person);
+                        self.state.loading = Loading::Idle;
+                    }
+                    Err(err) => match Loading::from_error(&err) {
+                        Some(loading) => self.state.loading = loading,
+                        None => {
+                            let message = match (err, self.id) {
+                                (DbError::NotFound(_), Some(id)) => {
+                                    format!("No entry with ID: {} found", id)
+                                }
+                                (err, _) => err.to_string(),
+                            };
+
+                            DialogService::alert(&message);
+                            self.go_to_persons_list();
+                        }
+                    },
+                }
+            }
             Msg::Save => {
-                if self.state.is_inserting {
-                    self.db_conn.borrow_mut().insert(&self.state.name_value);
-                } else {
-                    self.db_conn
-                        .borrow_mut()
-                        .update(self.id.unwrap(), &self.state.name_value);
+                if !self.state.form.validate_all() {
+                    return true;
                 }
 
-                if let Some(ref go_to_page) = self.go_to_persons_list {
-                    go_to_page.emit(());
+                self.save();
+            }
+            Msg::Saved(result) => {
+                self.request = None;
+                self.state.loading = Loading::Idle;
+
+                match result {
+                    Ok(()) => self.go_to_persons_list(),
+                    Err(DbError::Conflict(stored)) => self.state.conflict = Some(*stored),
+                    Err(err) => match Loading::from_error(&err) {
+                        Some(loading) => self.state.loading = loading,
+                        None => DialogService::alert(&err.to_string()),
+                    },
                 }
             }
-            Msg::Cancel => {
-                if let Some(ref go_to_page) = self.go_to_persons_list {
-                    go_to_page.emit(());
+            Msg::Checked(result) => {
+                self.check_request = None;
+                self.state.is_deleted = matches!(result, Err(DbError::NotFound(_)));
+            }
+            Msg::Resolve(resolution) => {
+                let conflict = match self.state.conflict.take() {
+                    Some(conflict) => conflict,
+                    None => return false,
+                };
+
+                match resolution {
+                    Resolution::Overwrite => {
+                        self.state.stored = Some(conflict);
+                        self.save();
+                    }
+                    Resolution::Merge => {
+                        if let Some(stored) = &self.state.stored {
+                            self.state.form.merge(stored, &conflict);
+                        }
+
+                        self.state.stored = Some(conflict);
+                    }
+                    Resolution::Discard => {
+                        self.state.form = PersonForm::from_person(&conflict);
+                        self.state.stored = Some(conflict);
+                    }
                 }
             }
+            Msg::Retry => {
+                // Saving only fails once the form was loaded
+                if self.state.is_inserting || self.state.stored.is_some() {
+                    self.save();
+                } else {
+                    self.load();
+                }
+            }
+            Msg::Cancel => self.go_to_persons_list(),
         }
 
         true
     }
 
     fn change(&mut self, props: Self::Properties) -> ShouldRender {
-        self.id = props.id;
-        self.state.name_value = props.name;
-        self.can_write = props.can_write;
-        self.state.is_inserting = props.id.is_none();
+        let reload = self.id != props.person_id;
+
+        self.id = props.person_id;
+        self.current_user = props.current_user;
         self.go_to_persons_list = props.go_to_persons_list;
-        self.db_conn = props.db_conn.unwrap();
+        self.async_conn = props.async_conn.unwrap();
+
+        if reload {
+            self.load();
+        } else if props.sync_count != self.sync_count && self.state.stored.is_some() {
+            if let Some(id) = self.id {
+                self.check_request = Some(
+                    self.async_conn
+                        .get_person(id, self.link.callback(Msg::Checked)),
+                );
+            }
+        }
+
+        self.sync_count = props.sync_count;
 
         true
     }
@@ -109,32 +469,54 @@ impl Component for Person {
                         disabled=true
                     />
                 </div>
-                <div>
-                    <label>{"Name: "}</label>
-                    <input
-                        type="text"
-                        value=&self.state.name_value
-                        disabled=!self.can_write
-                        oninput=self.link.callback(|e: InputData| Msg::ChangeName(e.value))
-                    />
-                </div>
+                <style>
+                    {r#"
+                        .field-error {
+                            color: #C00000;
+                            margin-left: 8px;
+                        }
+                        .conflict {
+                            border: 1px solid #C00000;
+                            padding: 8px;
+                        }
+                        .conflict .differs {
+                            font-weight: bold;
+                        }
+                    "#}
+                </style>
+                { for PersonField::ALL.iter().map(|field| self.view_field(*field)) }
+                { view_loading(&self.state.loading, self.link.callback(|_| Msg::Retry)) }
+                {
+                    if self.state.is_deleted {
+                        html! {
+                            <p class="field-error">
+                                {"This person was deleted in another tab, it can't be saved anymore"}
+                            </p>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+                { self.view_conflict() }
                 <div>
                     <button
                         onclick=self.link.callback(|_| Msg::Save)
-                        disabled=!self.can_write
+                        disabled=!self.can_save()
+                            || !self.state.form.errors.is_empty()
+                            || self.state.loading.is_busy()
+                            || self.state.conflict.is_some()
+                            || self.state.is_deleted
+                            || !(self.state.is_inserting || self.state.stored.is_some())
                     >
                         {
                             if self.state.is_inserting {
                                 "Insert"
                             } else {
-                                "Unpdate"
+                                "Update"
                             }
                         }
                     </button>
-                    <button
-                        onclick=self.link.callback(|_| Msg::Cancel)
-                        disabled=!self.can_write
-                    >
+                    <button onclick=self.link.callback(|_| Msg::Cancel)>
                         {"Cancel"}
                     </button>
                 </div>
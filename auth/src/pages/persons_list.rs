@@ -1,18 +1,102 @@
-use std::collections::HashSet;
+use std::collections::HashMap;
+use std::time::Duration;
+use wasm_bindgen::JsCast;
+use web_sys::{Element, Event, KeyboardEvent};
 use yew::prelude::*;
+use yew::services::timeout::{TimeoutService, TimeoutTask};
 use yew::services::DialogService;
 
-use crate::database::DbConn;
+use crate::database::{
+    AsyncConn, DbConn, DbResult, Pending, PersonsPage, PersonsQuery, PersonsSort, SortColumn,
+    PAGE_SIZES,
+};
+use crate::download;
+use crate::events::WindowListener;
+use crate::pages::{view_loading, Loading};
 use crate::person::Person;
+use crate::person_csv;
+use crate::search;
+use crate::user::{AuthenticatedUser, DbPrivilege};
+
+/// Time the user has to stop typing before the search is run
+const FILTER_DELAY: Duration = Duration::from_millis(300);
+
+/// Time a notification stays visible
+const NOTIFICATION_DELAY: Duration = Duration::from_secs(10);
+
+/// Message shown above the persons after a change
+struct Notification {
+    message: String,
+    /// Offers to undo the change that was just made
+    can_undo: bool,
+}
+
+/// Message for the Ctrl+Z and Ctrl+Shift+Z shortcuts, text fields keep
+/// their own undo
+fn shortcut(event: Event) -> Vec<Msg> {
+    let event = match event.dyn_into::<KeyboardEvent>() {
+        Ok(event) => event,
+        Err(_) => return Vec::new(),
+    };
+    let is_editing = event
+        .target()
+        .and_then(|target| target.dyn_into::<Element>().ok())
+        .is_some_and(|element| {
+            matches!(element.tag_name().as_str(), "INPUT" | "SELECT" | "TEXTAREA")
+        });
+
+    if is_editing
+        || !(event.ctrl_key() || event.meta_key())
+        || !event.key().eq_ignore_ascii_case("z")
+    {
+        return Vec::new();
+    }
+
+    event.prevent_default();
+
+    if event.shift_key() {
+        vec![Msg::Redo]
+    } else {
+        vec![Msg::Undo]
+    }
+}
 
 pub struct PersonsList {
     id_to_find: Option<u32>,
     name_portion: String,
+    match_case: bool,
+    fuzzy: bool,
+    /// Pending search, dropping it cancels the search
+    filter_task: Option<TimeoutTask>,
+    /// Persons in the current page only
     filtered_persons: Vec<Person>,
-    selected_ids: HashSet<u32>,
-    can_write: bool,
-    go_to_one_person_page: Option<Callback<Option<Person>>>,
+    /// Number of persons matching the filter across every page
+    total_persons: usize,
+    /// Similarity of the persons in the page when searching fuzzily
+    scores: HashMap<u32, f32>,
+    page: usize,
+    page_size: usize,
+    /// Selected persons, along with the version they were shown at
+    selected: HashMap<u32, u32>,
+    sort: Option<PersonsSort>,
+    loading: Loading,
+    /// Querying the current page, dropping it cancels the query
+    query_request: Option<Pending>,
+    /// Deleting the selection, dropping it cancels the delete
+    delete_request: Option<Pending>,
+    current_user: Option<AuthenticatedUser>,
+    go_to_one_person_page: Option<Callback<Option<u32>>>,
+    go_to_import_page: Option<Callback<()>>,
+    on_sort: Option<Callback<Option<PersonsSort>>>,
+    sync_count: usize,
+    notification: Option<Notification>,
+    /// Hides the notification, dropping it keeps the notification
+    notification_task: Option<TimeoutTask>,
+    /// The persons, wherever they are kept
+    async_conn: AsyncConn,
+    /// The `Database` in the browser, for the operations only it offers
     db_conn: DbConn,
+    _shortcuts: WindowListener,
     link: ComponentLink<Self>,
 }
 
@@ -21,30 +105,318 @@ pub enum Msg {
     IdChanged(String),
     Find,
     ChangePartialName(String),
+    ToggleMatchCase,
+    ToggleFuzzy,
     Filter,
+    Loaded(DbResult<PersonsPage>),
+    Reload,
     Delete,
+    Deleted(Vec<DbResult<()>>),
     Add,
+    Import,
+    Export,
     Edit(u32),
     ToggleSelect(u32),
+    Sort(SortColumn),
+    ChangePageSize(String),
+    GoToPage(usize),
+    Undo,
+    Redo,
+    DismissNotification,
 }
 
 #[derive(Properties, Clone)]
 pub struct Props {
-    pub can_write: bool,
-    pub go_to_one_person_page: Option<Callback<Option<Person>>>,
+    pub current_user: Option<AuthenticatedUser>,
+    pub go_to_one_person_page: Option<Callback<Option<u32>>>,
+    pub go_to_import_page: Option<Callback<()>>,
+    /// Sort kept by the parent so it survives leaving the page
+    pub sort: Option<PersonsSort>,
+    pub on_sort: Option<Callback<Option<PersonsSort>>>,
+    /// Increased every time another tab changes the data
+    pub sync_count: usize,
+    pub async_conn: Option<AsyncConn>,
     pub db_conn: Option<DbConn>,
 }
 
 impl Default for Props {
     fn default() -> Self {
         Self {
-            can_write: false,
+            current_user: None,
             go_to_one_person_page: None,
+            go_to_import_page: None,
+            sort: None,
+            on_sort: None,
+            sync_count: 0,
+            async_conn: None,
             db_conn: None,
         }
     }
 }
 
+impl PersonsList {
+    /// Only decides what is offered, the `Database` authorizes every change
+    fn can(&self, privilege: DbPrivilege) -> bool {
+        self.current_user
+            .as_ref()
+            .is_some_and(|user| user.has_privilege(privilege))
+    }
+
+    /// The persons matching the search, in the order they are shown
+    fn query(&self, offset: usize, limit: usize) -> PersonsQuery {
+        PersonsQuery {
+            text: self.name_portion.clone(),
+            match_case: self.match_case,
+            fuzzy: self.fuzzy,
+            sort: self.sort,
+            offset,
+            limit,
+        }
+    }
+
+    /// Queries the current page, the persons shown are replaced once it
+    /// is answered
+    fn reload(&mut self) {
+        let query = self.query(self.page * self.page_size, self.page_size);
+
+        self.loading = Loading::Busy;
+        self.query_request = Some(
+            self.async_conn
+                .query_persons(&query, self.link.callback(Msg::Loaded)),
+        );
+    }
+
+    /// Whether the persons are kept in the browser, undo, import and
+    /// export are only offered then
+    fn is_local(&self) -> bool {
+        !self.async_conn.is_remote()
+    }
+
+    /// Downloads the selected persons, or every person matching the search
+    /// when none is selected, as a CSV file
+    fn export(&self) -> Result<(), String> {
+        let db = self.db_conn.borrow();
+        let ids: Vec<u32> = db
+            .query_persons(&self.query(0, usize::MAX))
+            .persons
+            .iter()
+            .map(|person| person.id)
+            .filter(|id| self.selected.is_empty() || self.selected.contains_key(id))
+            .collect();
+        let persons = db.export_persons(&ids).map_err(|err| err.to_string())?;
+        let csv = person_csv::export(&persons)?;
+
+        download::download("persons.csv", "text/csv", &csv).map_err(|err| {
+            err.as_string()
+                .unwrap_or_else(|| "Unable to download".to_string())
+        })
+    }
+
+    fn notify(&mut self, message: String, can_undo: bool) {
+        self.notification = Some(Notification { message, can_undo });
+        self.notification_task = Some(TimeoutService::spawn(
+            NOTIFICATION_DELAY,
+            self.link.callback(|_| Msg::DismissNotification),
+        ));
+    }
+
+    fn last_page(&self) -> usize {
+        self.total_persons.saturating_sub(1) / self.page_size
+    }
+
+    fn view_pagination(&self) -> Html {
+        let first = self.page * self.page_size + 1;
+        let last = first + self.filtered_persons.len() - 1;
+        let page = self.page;
+        let last_page = self.last_page();
+        let is_first_page = page == 0;
+        let is_last_page = page >= last_page;
+
+        html! {
+            <div>
+                <button disabled=is_first_page onclick=self.link.callback(|_| Msg::GoToPage(0))>
+                    {"First"}
+                </button>
+                <button disabled=is_first_page onclick=self.link.callback(move |_| Msg::GoToPage(page - 1))>
+                    {"Previous"}
+                </button>
+                <span>{format!(" Showing {}–{} of {} ", first, last, self.total_persons)}</span>
+                <button disabled=is_last_page onclick=self.link.callback(move |_| Msg::GoToPage(page + 1))>
+                    {"Next"}
+                </button>
+                <button disabled=is_last_page onclick=self.link.callback(move |_| Msg::GoToPage(last_page))>
+                    {"Last"}
+                </button>
+                <label>{" Page size: "}</label>
+                <select
+                    onchange=self.link.callback(|e: ChangeData| match e {
+                        ChangeData::Select(select) => Msg::ChangePageSize(select.value()),
+                        _ => Msg::ChangePageSize(String::default()),
+                    })
+                >
+                    {
+                        for PAGE_SIZES.iter().map(|size| html! {
+                            <option value=size.to_string() selected={*size == self.page_size}>
+                                {size}
+                            </option>
+                        })
+                    }
+                </select>
+            </div>
+        }
+    }
+
+    /// Buttons of the operations the `Database` in the browser offers
+    fn view_local_actions(&self) -> Html {
+        if !self.is_local() {
+            return html! {};
+        }
+
+        html! {
+            <>
+                <button
+                    disabled=!(self.can(DbPrivilege::CanCreate) || self.can(DbPrivilege::CanUpdate))
+                    onclick=self.link.callback(|_| Msg::Import)
+                >
+                    {"Import CSV"}
+                </button>
+                <button
+                    disabled=!self.can(DbPrivilege::CanExport)
+                    title="Exports the selection, or every match when nothing is selected"
+                    onclick=self.link.callback(|_| Msg::Export)
+                >
+                    {"Export CSV"}
+                </button>
+                <button
+                    disabled=!self.db_conn.borrow().can_undo()
+                    title="Ctrl+Z"
+                    onclick=self.link.callback(|_| Msg::Undo)
+                >
+                    {"Undo"}
+                </button>
+                <button
+                    disabled=!self.db_conn.borrow().can_redo()
+                    title="Ctrl+Shift+Z"
+                    onclick=self.link.callback(|_| Msg::Redo)
+                >
+                    {"Redo"}
+                </button>
+            </>
+        }
+    }
+
+    fn view_notification(&self) -> Html {
+        let notification = match &self.notification {
+            Some(notification) => notification,
+            None => return html! {},
+        };
+
+        html! {
+            <p class="notification">
+                {&notification.message}
+                {
+                    if notification.can_undo {
+                        html! {
+                            <button onclick=self.link.callback(|_| Msg::Undo)>
+                                {"Undo"}
+                            </button>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+                <button onclick=self.link.callback(|_| Msg::DismissNotification)>
+                    {"×"}
+                </button>
+            </p>
+        }
+    }
+
+    fn view_sortable_header(&self, column: SortColumn, title: &str) -> Html {
+        // Fuzzy results are always ranked by their score
+        if !self.scores.is_empty() {
+            return html! { <th>{title}</th> };
+        }
+
+        let indicator = match self.sort {
+            Some(sort) if sort.column == column && sort.ascending => " ▲",
+            Some(sort) if sort.column == column => " ▼",
+            _ => "",
+        };
+
+        html! {
+            <th class="sortable" onclick=self.link.callback(move |_| Msg::Sort(column))>
+                {title}{indicator}
+            </th>
+        }
+    }
+
+    /// `text` with the parts matching the search wrapped in `mark`
+    fn view_highlighted(&self, text: &str) -> Html {
+        // Fuzzy matches don't map to substrings of the text
+        if self.fuzzy {
+            return html! { {text} };
+        }
+
+        let mut parts = Vec::new();
+        let mut start = 0;
+
+        for range in search::find_matches(text, &self.name_portion, self.match_case) {
+            parts.push(html! { {&text[start..range.start]} });
+            parts.push(html! { <mark>{&text[range.clone()]}</mark> });
+            start = range.end;
+        }
+
+        parts.push(html! { {&text[start..]} });
+
+        html! { <>{ for parts }</> }
+    }
+
+    fn view_row(&self, person: &Person) -> Html {
+        let id = person.id;
+        let on_toggle_select = self.link.callback(move |_| Msg::ToggleSelect(id));
+        let on_edit = self.link.callback(move |_| Msg::Edit(id));
+        let birth_date = person
+            .birth_date
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+        let address = person
+            .address
+            .as_ref()
+            .map(|address| address.to_string())
+            .unwrap_or_default();
+
+        html! {
+            <tr>
+                <td>
+                    <input
+                        type="checkbox"
+                        oninput=on_toggle_select
+                        checked=self.selected.contains_key(&id)
+                    />
+                </td>
+                <td>
+                    <button onclick=on_edit>
+                        {"Edit"}
+                    </button>
+                </td>
+                <td>{id}</td>
+                <td>{ self.view_highlighted(&person.name) }</td>
+                <td>{person.email.clone().unwrap_or_default()}</td>
+                <td>{person.phone.clone().unwrap_or_default()}</td>
+                <td>{birth_date}</td>
+                <td>{address}</td>
+                {
+                    match self.scores.get(&id) {
+                        Some(score) => html! { <td>{format!("{:.0}%", score * 100.0)}</td> },
+                        None => html! {},
+                    }
+                }
+            </tr>
+        }
+    }
+}
+
 impl Component for PersonsList {
     type Message = Msg;
     type Properties = Props;
@@ -53,20 +425,33 @@ impl Component for PersonsList {
         let mut model = PersonsList {
             id_to_find: None,
             name_portion: String::default(),
+            match_case: false,
+            fuzzy: false,
+            filter_task: None,
             filtered_persons: Vec::<Person>::new(),
-            selected_ids: HashSet::<u32>::new(),
-            can_write: props.can_write,
+            total_persons: 0,
+            scores: HashMap::new(),
+            page: 0,
+            page_size: PAGE_SIZES[0],
+            selected: HashMap::new(),
+            sort: props.sort,
+            loading: Loading::Idle,
+            query_request: None,
+            delete_request: None,
+            current_user: props.current_user,
             go_to_one_person_page: props.go_to_one_person_page,
+            go_to_import_page: props.go_to_import_page,
+            on_sort: props.on_sort,
+            sync_count: props.sync_count,
+            notification: None,
+            notification_task: None,
+            async_conn: props.async_conn.unwrap(),
             db_conn: props.db_conn.unwrap(),
+            _shortcuts: WindowListener::new("keydown", link.batch_callback(shortcut)),
             link,
         };
 
-        model.filtered_persons = model
-            .db_conn
-            .borrow()
-            .get_persons_by_name("")
-            .map(|p| p.to_owned())
-            .collect();
+        model.reload();
 
         model
     }
@@ -82,27 +467,89 @@ impl Component for PersonsList {
                     DialogService::alert("No ID provided");
                 }
             },
-            Msg::ChangePartialName(value) => self.name_portion = value,
+            Msg::ChangePartialName(value) => {
+                self.name_portion = value;
+                self.filter_task = Some(TimeoutService::spawn(
+                    FILTER_DELAY,
+                    self.link.callback(|_| Msg::Filter),
+                ));
+
+                return false;
+            }
+            Msg::ToggleMatchCase => {
+                self.match_case = !self.match_case;
+                self.page = 0;
+                self.reload();
+            }
+            Msg::ToggleFuzzy => {
+                self.fuzzy = !self.fuzzy;
+                self.page = 0;
+                self.reload();
+            }
             Msg::Filter => {
-                self.filtered_persons = self
-                    .db_conn
-                    .borrow()
-                    .get_persons_by_name(self.name_portion.as_str())
-                    .map(|p| p.to_owned())
-                    .collect();
+                self.filter_task = None;
+                self.page = 0;
+                self.reload();
             }
+            Msg::Loaded(Ok(page)) => {
+                self.query_request = None;
+                self.loading = Loading::Idle;
+                self.filtered_persons = page.persons;
+                self.total_persons = page.total;
+                self.scores = page.scores;
+
+                // Deleting the selection deletes what is shown
+                for person in &self.filtered_persons {
+                    if let Some(version) = self.selected.get_mut(&person.id) {
+                        *version = person.version;
+                    }
+                }
+
+                // The current page no longer exists, move back to the last one
+                if self.page > 0 && self.page > self.last_page() {
+                    self.page = self.last_page();
+                    self.reload();
+                }
+            }
+            Msg::Loaded(Err(err)) => {
+                self.query_request = None;
+                self.loading = Loading::Failed(err.to_string());
+            }
+            Msg::Reload => self.reload(),
             Msg::Delete => {
                 if DialogService::confirm("Are you sure you want to delete the entries selected?") {
-                    {
-                        let mut db = self.db_conn.borrow_mut();
-    
-                        for id in &self.selected_ids {
-                            db.delete(*id);
-                        }
-                    }
-                        
-                    self.update(Msg::Filter);
-                    DialogService::alert("Entries deleted successfully");
+                    let persons = self.selected.iter().map(|(id, version)| (*id, *version));
+
+                    self.delete_request = Some(
+                        self.async_conn
+                            .delete(persons.collect(), self.link.callback(Msg::Deleted)),
+                    );
+                }
+            }
+            Msg::Deleted(results) => {
+                self.delete_request = None;
+
+                let deleted = results.iter().filter(|result| result.is_ok()).count();
+                let errors: Vec<String> = results
+                    .into_iter()
+                    .filter_map(|result| result.err())
+                    .map(|err| err.to_string())
+                    .collect();
+
+                self.selected.clear();
+                self.reload();
+
+                if deleted > 0 {
+                    let can_undo = self.is_local();
+
+                    self.notify(format!("{} entries moved to the trash ", deleted), can_undo);
+                }
+
+                if !errors.is_empty() {
+                    DialogService::alert(&format!(
+                        "Some entries couldn't be deleted:\n{}",
+                        errors.join("\n")
+                    ));
                 }
             }
             Msg::Add => {
@@ -110,36 +557,101 @@ impl Component for PersonsList {
                     go_to_page.emit(None);
                 }
             }
-            Msg::ToggleSelect(id) => {
-                if self.selected_ids.contains(&id) {
-                    self.selected_ids.remove(&id);
-                } else {
-                    self.selected_ids.insert(id);
+            Msg::Import => {
+                if let Some(ref go_to_page) = self.go_to_import_page {
+                    go_to_page.emit(());
+                }
+            }
+            Msg::Export => {
+                if let Err(err) = self.export() {
+                    DialogService::alert(&err);
                 }
+
+                return false;
+            }
+            Msg::Sort(column) => {
+                self.sort = Some(PersonsSort::toggle(self.sort, column));
+                self.page = 0;
+                self.reload();
+
+                if let Some(ref on_sort) = self.on_sort {
+                    on_sort.emit(self.sort);
+                }
+            }
+            Msg::ChangePageSize(size) => {
+                if let Ok(size) = size.parse::<usize>() {
+                    // Keep the first person of the current page visible
+                    self.page = self.page * self.page_size / size;
+                    self.page_size = size;
+                    self.reload();
+                }
+            }
+            Msg::GoToPage(page) => {
+                self.page = page;
+                self.reload();
             }
-            Msg::Edit(id) => match self.db_conn.borrow().get_person_by_id(id) {
-                Some(person) => {
-                    if let Some(ref go_to_page) = self.go_to_one_person_page {
-                        go_to_page.emit(Some(person.clone()));
+            Msg::Undo | Msg::Redo if !self.is_local() => return false,
+            Msg::Undo => {
+                let result = self.db_conn.borrow_mut().undo();
+
+                match result {
+                    Ok(description) => self.notify(format!("Undone: {} ", description), false),
+                    Err(err) => DialogService::alert(&err.to_string()),
+                }
+
+                self.reload();
+            }
+            Msg::Redo => {
+                let result = self.db_conn.borrow_mut().redo();
+
+                match result {
+                    Ok(description) => self.notify(format!("Redone: {} ", description), false),
+                    Err(err) => DialogService::alert(&err.to_string()),
+                }
+
+                self.reload();
+            }
+            Msg::DismissNotification => {
+                self.notification = None;
+                self.notification_task = None;
+            }
+            Msg::ToggleSelect(id) => {
+                if self.selected.remove(&id).is_none() {
+                    if let Some(person) = self.filtered_persons.iter().find(|p| p.id == id) {
+                        self.selected.insert(id, person.version);
                     }
                 }
-                None => DialogService::alert(&format!("No entry with ID: {} found", id)),
-            },
+            }
+            // The person page reports persons that don't exist
+            Msg::Edit(id) => {
+                if let Some(ref go_to_page) = self.go_to_one_person_page {
+                    go_to_page.emit(Some(id));
+                }
+            }
         }
 
         true
     }
 
     fn change(&mut self, props: Self::Properties) -> ShouldRender {
-        self.can_write = props.can_write;
+        // The parent renders again whenever its own state changes, the page
+        // is only queried again when the sort, the user or the data changed
+        let reload = props.sort != self.sort
+            || props.current_user != self.current_user
+            || props.sync_count != self.sync_count;
+
+        self.current_user = props.current_user;
         self.go_to_one_person_page = props.go_to_one_person_page;
+        self.go_to_import_page = props.go_to_import_page;
+        self.sort = props.sort;
+        self.on_sort = props.on_sort;
+        self.sync_count = props.sync_count;
+        self.async_conn = props.async_conn.unwrap();
         self.db_conn = props.db_conn.unwrap();
-        self.filtered_persons = self
-            .db_conn
-            .borrow()
-            .get_persons_by_name("")
-            .map(|p| p.to_owned())
-            .collect();
+
+        if reload {
+            self.reload();
+        }
 
         true
     }
@@ -147,6 +659,16 @@ impl Component for PersonsList {
     fn view(&self) -> Html {
         html! {
             <div>
+                <style>
+                    {r#"
+                        .sortable {
+                            cursor: pointer;
+                        }
+                        .notification {
+                            color: #006000;
+                        }
+                    "#}
+                </style>
                 <div>
                     <label>{"Id: "}</label>
                     <input
@@ -158,27 +680,43 @@ impl Component for PersonsList {
                     </button>
                 </div>
                 <div>
-                    <label>{"Name portion: "}</label>
+                    <label>{"Search: "}</label>
                     <input
                         type="text"
                         oninput=self.link.callback(|e: InputData| Msg::ChangePartialName(e.value))
                     />
-                    <button onclick=self.link.callback(|_| Msg::Filter)>
-                        {"Filter"}
-                    </button>
+                    <label>
+                        <input
+                            type="checkbox"
+                            checked=self.match_case
+                            onclick=self.link.callback(|_| Msg::ToggleMatchCase)
+                        />
+                        {"Match case"}
+                    </label>
+                    <label>
+                        <input
+                            type="checkbox"
+                            checked=self.fuzzy
+                            onclick=self.link.callback(|_| Msg::ToggleFuzzy)
+                        />
+                        {"Fuzzy"}
+                    </label>
                 </div>
                 <button
-                    disabled=!self.can_write
+                    disabled=!self.can(DbPrivilege::CanDelete) || self.delete_request.is_some()
                     onclick=self.link.callback(|_| Msg::Delete)
                 >
                     {"Delete Selection"}
                 </button>
                 <button
-                    disabled=!self.can_write
+                    disabled=!self.can(DbPrivilege::CanCreate)
                     onclick=self.link.callback(|_| Msg::Add)
                 >
                     {"Add New"}
                 </button>
+                { self.view_local_actions() }
+                { self.view_notification() }
+                { view_loading(&self.loading, self.link.callback(|_| Msg::Reload)) }
                 {
                     if self.filtered_persons.is_empty() {
                         html! {
@@ -190,42 +728,34 @@ impl Component for PersonsList {
                                 <thead>
                                     <th></th>
                                     <th></th>
-                                    <th>{"ID"}</th>
-                                    <th>{"Name"}</th>
-                                </thead>
-                                <tbody>
+                                    { self.view_sortable_header(SortColumn::Id, "ID") }
+                                    { self.view_sortable_header(SortColumn::Name, "Name") }
+                                    <th>{"Email"}</th>
+                                    <th>{"Phone"}</th>
+                                    <th>{"Birth Date"}</th>
+                                    <th>{"Address"}</th>
                                     {
-                                        for self.filtered_persons.iter().map(|person| {
-                                            let id = person.id;
-                                            let name = person.name.clone();
-                                            let on_toggle_select = self.link.callback(move |_| Msg::ToggleSelect(id));
-                                            let on_edit = self.link.callback(move |_| Msg::Edit(id));
-
-                                            html! {
-                                                <tr>
-                                                    <td>
-                                                        <input
-                                                            type="checkbox"
-                                                            oninput=on_toggle_select
-                                                            checked=self.selected_ids.contains(&id)
-                                                        />
-                                                    </td>
-                                                    <td>
-                                                        <button onclick=on_edit>
-                                                            {"Edit"}
-                                                        </button>
-                                                    </td>
-                                                    <td>{id}</td>
-                                                    <td>{name}</td>
-                                                </tr>
-                                            }
-                                        })
+                                        if self.scores.is_empty() {
+                                            html! {}
+                                        } else {
+                                            html! { <th>{"Score"}</th> }
+                                        }
                                     }
+                                </thead>
+                                <tbody>
+                                    { for self.filtered_persons.iter().map(|person| self.view_row(person)) }
                                 </tbody>
                             </table>
                         }
                     }
                 }
+                {
+                    if self.filtered_persons.is_empty() {
+                        html! {}
+                    } else {
+                        self.view_pagination()
+                    }
+                }
             </div>
         }
     }
@@ -0,0 +1,189 @@
+use chrono::Local;
+use yew::prelude::*;
+use yew::services::DialogService;
+
+use crate::database::{DbConn, DbResult, DeletedPerson};
+use crate::user::{AuthenticatedUser, DbPrivilege};
+
+pub struct Trash {
+    deleted_persons: Vec<DeletedPerson>,
+    retention_days: String,
+    current_user: Option<AuthenticatedUser>,
+    db_conn: DbConn,
+    link: ComponentLink<Self>,
+}
+
+#[derive(Debug)]
+pub enum Msg {
+    Restore(u32),
+    Purge(u32),
+    ChangeRetentionDays(String),
+    SaveRetentionDays,
+}
+
+#[derive(Properties, Clone, Default)]
+pub struct Props {
+    pub current_user: Option<AuthenticatedUser>,
+    pub db_conn: Option<DbConn>,
+}
+
+impl Trash {
+    fn reload(&mut self) {
+        let db = self.db_conn.borrow();
+
+        self.deleted_persons = db.get_trash();
+        self.retention_days = db.retention_days().to_string();
+    }
+
+    /// Reloads the trash after a change, or tells the user why it failed
+    fn handle_result(&mut self, result: DbResult<()>) {
+        if let Err(err) = result {
+            DialogService::alert(&err.to_string());
+        }
+
+        self.reload();
+    }
+
+    /// Only decides what is offered, the `Database` authorizes every change
+    fn can(&self, privilege: DbPrivilege) -> bool {
+        self.current_user
+            .as_ref()
+            .is_some_and(|user| user.has_privilege(privilege))
+    }
+
+    fn view_row(&self, deleted: &DeletedPerson) -> Html {
+        let id = deleted.person.id;
+        let deleted_at = deleted
+            .deleted_at
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
+
+        html! {
+            <tr>
+                <td>{id}</td>
+                <td>{&deleted.person.name}</td>
+                <td>{deleted_at}</td>
+                <td>{&deleted.deleted_by}</td>
+                <td>
+                    <button
+                        disabled=!self.can(DbPrivilege::CanCreate)
+                        onclick=self.link.callback(move |_| Msg::Restore(id))
+                    >
+                        {"Restore"}
+                    </button>
+                    <button
+                        disabled=!self.can(DbPrivilege::CanDelete)
+                        onclick=self.link.callback(move |_| Msg::Purge(id))
+                    >
+                        {"Purge"}
+                    </button>
+                </td>
+            </tr>
+        }
+    }
+}
+
+impl Component for Trash {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let mut model = Trash {
+            deleted_persons: Vec::new(),
+            retention_days: String::default(),
+            current_user: props.current_user,
+            db_conn: props.db_conn.unwrap(),
+            link,
+        };
+
+        model.reload();
+
+        model
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Restore(id) => {
+                let result = self.db_conn.borrow_mut().restore(id);
+
+                self.handle_result(result);
+            }
+            Msg::Purge(id) => {
+                if DialogService::confirm(
+                    "Are you sure you want to purge this entry? It can't be undone",
+                ) {
+                    let result = self.db_conn.borrow_mut().purge(id);
+
+                    self.handle_result(result);
+                }
+            }
+            Msg::ChangeRetentionDays(value) => self.retention_days = value,
+            Msg::SaveRetentionDays => match self.retention_days.trim().parse::<u32>() {
+                Ok(days) => {
+                    let result = self.db_conn.borrow_mut().set_retention_days(days);
+
+                    self.handle_result(result);
+                }
+                Err(_) => DialogService::alert("The number of days must be a whole number"),
+            },
+        }
+
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.current_user = props.current_user;
+        self.db_conn = props.db_conn.unwrap();
+        self.reload();
+
+        true
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <div>
+                <h3>{"Trash"}</h3>
+                <div>
+                    <label>{"Keep deleted persons for "}</label>
+                    <input
+                        type="number"
+                        min="1"
+                        value=&self.retention_days
+                        disabled=!self.can(DbPrivilege::CanDelete)
+                        oninput=self.link.callback(|e: InputData| Msg::ChangeRetentionDays(e.value))
+                    />
+                    <label>{" days "}</label>
+                    <button
+                        disabled=!self.can(DbPrivilege::CanDelete)
+                        onclick=self.link.callback(|_| Msg::SaveRetentionDays)
+                    >
+                        {"Save"}
+                    </button>
+                </div>
+                {
+                    if self.deleted_persons.is_empty() {
+                        html! {
+                            <p>{"The trash is empty"}</p>
+                        }
+                    } else {
+                        html! {
+                            <table>
+                                <thead>
+                                    <th>{"ID"}</th>
+                                    <th>{"Name"}</th>
+                                    <th>{"Deleted At"}</th>
+                                    <th>{"Deleted By"}</th>
+                                    <th></th>
+                                </thead>
+                                <tbody>
+                                    { for self.deleted_persons.iter().map(|deleted| self.view_row(deleted)) }
+                                </tbody>
+                            </table>
+                        }
+                    }
+                }
+            </div>
+        }
+    }
+}
@@ -0,0 +1,352 @@
+use yew::prelude::*;
+use yew::services::DialogService;
+
+use crate::database::{DbConn, DbResult};
+use crate::user::{AuthenticatedUser, PasswordHash, Role, User};
+
+pub struct UsersList {
+    users: Vec<User>,
+    current_user: Option<AuthenticatedUser>,
+    new_username: String,
+    new_password: String,
+    new_roles: Vec<Role>,
+    resetting_password_for: Option<String>,
+    reset_password_value: String,
+    db_conn: DbConn,
+    link: ComponentLink<Self>,
+}
+
+#[derive(Debug)]
+pub enum Msg {
+    ChangeNewUsername(String),
+    ChangeNewPassword(String),
+    ToggleNewRole(Role),
+    Create,
+    ToggleDisabled(String),
+    Delete(String),
+    ToggleRole(String, Role),
+    StartResetPassword(String),
+    ChangeResetPassword(String),
+    ResetPassword,
+    CancelResetPassword,
+}
+
+#[derive(Properties, Clone, Default)]
+pub struct Props {
+    pub current_user: Option<AuthenticatedUser>,
+    pub db_conn: Option<DbConn>,
+}
+
+impl UsersList {
+    fn reload(&mut self) {
+        self.users = self.db_conn.borrow().get_all_users();
+    }
+
+    /// Reloads the users after a change, or tells the user why it failed
+    fn handle_result(&mut self, result: DbResult<()>) {
+        if let Err(err) = result {
+            DialogService::alert(&err.to_string());
+        }
+
+        self.reload();
+    }
+
+    fn is_current_user(&self, username: &str) -> bool {
+        self.current_user
+            .as_ref()
+            .is_some_and(|user| user.username == username)
+    }
+
+    fn view_row(&self, user: &User) -> Html {
+        let username = user.username.clone();
+        let on_toggle_disabled = {
+            let username = username.clone();
+            self.link
+                .callback(move |_| Msg::ToggleDisabled(username.clone()))
+        };
+        let on_delete = {
+            let username = username.clone();
+            self.link.callback(move |_| Msg::Delete(username.clone()))
+        };
+        let on_reset_password = {
+            let username = username.clone();
+            self.link
+                .callback(move |_| Msg::StartResetPassword(username.clone()))
+        };
+        let privileges: Vec<&str> = user.privileges().iter().map(|p| p.label()).collect();
+        let privileges = privileges.join(", ");
+
+        html! {
+            <tr>
+                <td>
+                    {&user.username}
+                    {
+                        if self.is_current_user(&user.username) {
+                            " (you)"
+                        } else {
+                            ""
+                        }
+                    }
+                </td>
+                {
+                    for Role::ALL.iter().map(|role| {
+                        let role = *role;
+                        let username = username.clone();
+                        let on_toggle = self.link.callback(move |_| {
+                            Msg::ToggleRole(username.clone(), role)
+                        });
+
+                        html! {
+                            <td>
+                                <input
+                                    type="checkbox"
+                                    checked=user.has_role(role)
+                                    onclick=on_toggle
+                                />
+                            </td>
+                        }
+                    })
+                }
+                <td>{privileges}</td>
+                <td>{ if user.disabled { "Disabled" } else { "Active" } }</td>
+                <td>
+                    <button onclick=on_toggle_disabled>
+                        { if user.disabled { "Enable" } else { "Disable" } }
+                    </button>
+                    {
+                        if self.resetting_password_for.as_deref() == Some(user.username.as_str()) {
+                            html! {
+                                <span>
+                                    <input
+                                        type="password"
+                                        value=&self.reset_password_value
+                                        oninput=self.link.callback(|e: InputData| Msg::ChangeResetPassword(e.value))
+                                    />
+                                    <button onclick=self.link.callback(|_| Msg::ResetPassword)>
+                                        {"Save Password"}
+                                    </button>
+                                    <button onclick=self.link.callback(|_| Msg::CancelResetPassword)>
+                                        {"Cancel"}
+                                    </button>
+                                </span>
+                            }
+                        } else {
+                            html! {
+                                <button onclick=on_reset_password>
+                                    {"Reset Password"}
+                                </button>
+                            }
+                        }
+                    }
+                    <button onclick=on_delete>
+                        {"Delete"}
+                    </button>
+                </td>
+            </tr>
+        }
+    }
+}
+
+impl Component for UsersList {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let mut model = UsersList {
+            users: Vec::new(),
+            current_user: props.current_user,
+            new_username: String::default(),
+            new_password: String::default(),
+            new_roles: vec![Role::Viewer],
+            resetting_password_for: None,
+            reset_password_value: String::default(),
+            db_conn: props.db_conn.unwrap(),
+            link,
+        };
+
+        model.reload();
+
+        model
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::ChangeNewUsername(value) => self.new_username = value,
+            Msg::ChangeNewPassword(value) => self.new_password = value,
+            Msg::ToggleNewRole(role) => {
+                if self.new_roles.contains(&role) {
+                    self.new_roles.retain(|r| *r != role);
+                } else {
+                    self.new_roles.push(role);
+                }
+            }
+            Msg::Create => {
+                if self.new_password.is_empty() {
+                    DialogService::alert("A password is required");
+
+                    return true;
+                }
+
+                let password = PasswordHash::new(&std::mem::take(&mut self.new_password));
+                let result = self.db_conn.borrow_mut().insert_user(
+                    &self.new_username,
+                    password,
+                    self.new_roles.clone(),
+                );
+
+                if result.is_ok() {
+                    self.new_username.clear();
+                    self.new_roles = vec![Role::Viewer];
+                }
+
+                self.handle_result(result);
+            }
+            Msg::ToggleDisabled(username) => {
+                if self.is_current_user(&username) {
+                    DialogService::alert("You can't disable your own user");
+
+                    return true;
+                }
+
+                let disabled = self
+                    .users
+                    .iter()
+                    .any(|u| u.username == username && u.disabled);
+                let result = self
+                    .db_conn
+                    .borrow_mut()
+                    .set_user_disabled(&username, !disabled);
+
+                self.handle_result(result);
+            }
+            Msg::Delete(username) => {
+                if self.is_current_user(&username) {
+                    DialogService::alert("You can't delete your own user");
+                } else if DialogService::confirm(&format!(
+                    "Are you sure you want to delete the user {}?",
+                    username
+                )) {
+                    let result = self.db_conn.borrow_mut().delete_user(&username);
+
+                    self.handle_result(result);
+                }
+            }
+            Msg::ToggleRole(username, role) => {
+                let granted = self
+                    .users
+                    .iter()
+                    .any(|u| u.username == username && u.has_role(role));
+                let result = if granted {
+                    self.db_conn.borrow_mut().revoke_role(&username, role)
+                } else {
+                    self.db_conn.borrow_mut().grant_role(&username, role)
+                };
+
+                self.handle_result(result);
+            }
+            Msg::StartResetPassword(username) => {
+                self.resetting_password_for = Some(username);
+                self.reset_password_value.clear();
+            }
+            Msg::ChangeResetPassword(value) => self.reset_password_value = value,
+            Msg::ResetPassword => {
+                if self.reset_password_value.is_empty() {
+                    DialogService::alert("A password is required");
+
+                    return true;
+                }
+
+                if let Some(username) = self.resetting_password_for.take() {
+                    let password =
+                        PasswordHash::new(&std::mem::take(&mut self.reset_password_value));
+                    let result = self
+                        .db_conn
+                        .borrow_mut()
+                        .reset_password(&username, password);
+
+                    if result.is_ok() {
+                        DialogService::alert(&format!("Password of {} was reset", username));
+                    }
+
+                    self.handle_result(result);
+                }
+            }
+            Msg::CancelResetPassword => {
+                self.resetting_password_for = None;
+                self.reset_password_value.clear();
+            }
+        }
+
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.current_user = props.current_user;
+        self.db_conn = props.db_conn.unwrap();
+        self.reload();
+
+        true
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <div>
+                <h3>{"Users"}</h3>
+                <table>
+                    <thead>
+                        <th>{"Username"}</th>
+                        {
+                            for Role::ALL.iter().map(|role| html! {
+                                <th>{role.label()}</th>
+                            })
+                        }
+                        <th>{"Privileges"}</th>
+                        <th>{"Status"}</th>
+                        <th></th>
+                    </thead>
+                    <tbody>
+                        { for self.users.iter().map(|user| self.view_row(user)) }
+                    </tbody>
+                </table>
+                <h3>{"New User"}</h3>
+                <div>
+                    <label>{"Username: "}</label>
+                    <input
+                        type="text"
+                        value=&self.new_username
+                        oninput=self.link.callback(|e: InputData| Msg::ChangeNewUsername(e.value))
+                    />
+                </div>
+                <div>
+                    <label>{"Password: "}</label>
+                    <input
+                        type="password"
+                        value=&self.new_password
+                        oninput=self.link.callback(|e: InputData| Msg::ChangeNewPassword(e.value))
+                    />
+                </div>
+                <div>
+                    {
+                        for Role::ALL.iter().map(|role| {
+                            let role = *role;
+
+                            html! {
+                                <label>
+                                    <input
+                                        type="checkbox"
+                                        checked=self.new_roles.contains(&role)
+                                        onclick=self.link.callback(move |_| Msg::ToggleNewRole(role))
+                                    />
+                                    {role.label()}
+                                </label>
+                            }
+                        })
+                    }
+                </div>
+                <button onclick=self.link.callback(|_| Msg::Create)>
+                    {"Create User"}
+                </button>
+            </div>
+        }
+    }
+}
@@ -1,7 +1,113 @@
-#[derive(Debug, Clone)]
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::validation::{self, Rule, DATE_FORMAT};
+
+/// Fields of a `Person` that can be edited and validated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PersonField {
+    Name,
+    Email,
+    Phone,
+    BirthDate,
+    Street,
+    City,
+    PostalCode,
+    Country,
+}
+
+impl PersonField {
+    pub const ALL: [PersonField; 8] = [
+        PersonField::Name,
+        PersonField::Email,
+        PersonField::Phone,
+        PersonField::BirthDate,
+        PersonField::Street,
+        PersonField::City,
+        PersonField::PostalCode,
+        PersonField::Country,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PersonField::Name => "Name",
+            PersonField::Email => "Email",
+            PersonField::Phone => "Phone",
+            PersonField::BirthDate => "Birth Date",
+            PersonField::Street => "Street",
+            PersonField::City => "City",
+            PersonField::PostalCode => "Postal Code",
+            PersonField::Country => "Country",
+        }
+    }
+
+    /// Rules a value of this field must satisfy, both the `Person` form
+    /// and the `Database` check them
+    pub fn rules(self) -> &'static [Rule] {
+        match self {
+            PersonField::Name => &[Rule::Required, Rule::MaxLength(100)],
+            PersonField::Email => &[Rule::MaxLength(254), Rule::Email],
+            PersonField::Phone => &[Rule::MaxLength(32), Rule::Phone],
+            PersonField::BirthDate => &[Rule::PastDate { min_year: 1900 }],
+            PersonField::Street | PersonField::City => &[Rule::MaxLength(100)],
+            PersonField::PostalCode => &[Rule::MaxLength(16)],
+            PersonField::Country => &[Rule::MaxLength(56)],
+        }
+    }
+
+    pub fn validate(self, value: &str) -> Result<(), String> {
+        validation::validate(value, self.rules())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Address {
+    pub street: String,
+    pub city: String,
+    pub postal_code: String,
+    pub country: String,
+}
+
+impl Address {
+    pub fn is_empty(&self) -> bool {
+        self.fields().iter().all(|field| field.trim().is_empty())
+    }
+
+    fn fields(&self) -> [&str; 4] {
+        [&self.street, &self.city, &self.postal_code, &self.country]
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields: Vec<&str> = self
+            .fields()
+            .iter()
+            .map(|field| field.trim())
+            .filter(|field| !field.is_empty())
+            .collect();
+
+        write!(f, "{}", fields.join(", "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Person {
     pub id: u32,
     pub name: String,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub phone: Option<String>,
+    #[serde(default)]
+    pub birth_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub address: Option<Address>,
+    /// Increased by the `Database` on every change, updates and deletes of
+    /// an older version are rejected as conflicts
+    #[serde(default)]
+    pub version: u32,
 }
 
 impl Person {
@@ -9,6 +115,107 @@ impl Person {
         Self {
             id,
             name: name.to_string(),
+            email: None,
+            phone: None,
+            birth_date: None,
+            address: None,
+            version: 0,
+        }
+    }
+
+    /// Text of `field` as it is shown in forms, empty when not set
+    pub fn field_value(&self, field: PersonField) -> String {
+        let address = self.address.as_ref();
+
+        match field {
+            PersonField::Name => self.name.clone(),
+            PersonField::Email => self.email.clone().unwrap_or_default(),
+            PersonField::Phone => self.phone.clone().unwrap_or_default(),
+            PersonField::BirthDate => self
+                .birth_date
+                .map(|date| date.format(DATE_FORMAT).to_string())
+                .unwrap_or_default(),
+            PersonField::Street => address.map(|a| a.street.clone()).unwrap_or_default(),
+            PersonField::City => address.map(|a| a.city.clone()).unwrap_or_default(),
+            PersonField::PostalCode => address.map(|a| a.postal_code.clone()).unwrap_or_default(),
+            PersonField::Country => address.map(|a| a.country.clone()).unwrap_or_default(),
+        }
+    }
+
+    /// Builds a person from the text of its fields, as typed in a form or
+    /// read from a file. Blank optional fields become `None` and a birth
+    /// date that can't be parsed is dropped, so the values should be
+    /// validated first with `Person::validate_values`
+    pub fn from_values<F>(id: u32, value: F) -> Self
+    where
+        F: Fn(PersonField) -> String,
+    {
+        let value = |field: PersonField| value(field).trim().to_string();
+        let optional = |field: PersonField| Some(value(field)).filter(|v| !v.is_empty());
+        let address = Address {
+            street: value(PersonField::Street),
+            city: value(PersonField::City),
+            postal_code: value(PersonField::PostalCode),
+            country: value(PersonField::Country),
+        };
+
+        Self {
+            id,
+            name: value(PersonField::Name),
+            email: optional(PersonField::Email),
+            phone: optional(PersonField::Phone),
+            birth_date: optional(PersonField::BirthDate)
+                .and_then(|date| NaiveDate::parse_from_str(&date, DATE_FORMAT).ok()),
+            address: if address.is_empty() {
+                None
+            } else {
+                Some(address)
+            },
+            version: 0,
         }
     }
+
+    /// Checks the text of every field against its rules, returning the
+    /// failing ones
+    pub fn validate_values<F>(value: F) -> Result<(), Vec<(PersonField, String)>>
+    where
+        F: Fn(PersonField) -> String,
+    {
+        let errors: Vec<(PersonField, String)> = PersonField::ALL
+            .iter()
+            .filter_map(|field| {
+                field
+                    .validate(&value(*field))
+                    .err()
+                    .map(|message| (*field, message))
+            })
+            .collect();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Checks every field against its rules, returning the failing ones
+    pub fn validate(&self) -> Result<(), Vec<(PersonField, String)>> {
+        Person::validate_values(|field| self.field_value(field))
+    }
+
+    /// Every searchable text of the record, used to match a search
+    /// against any of the fields
+    pub fn searchable_fields(&self) -> Vec<String> {
+        let mut fields = vec![self.name.clone()];
+
+        fields.extend(self.email.clone());
+        fields.extend(self.phone.clone());
+        fields.extend(self.birth_date.map(|date| date.to_string()));
+
+        if let Some(address) = &self.address {
+            fields.extend(address.fields().iter().map(|field| field.to_string()));
+        }
+
+        fields
+    }
 }
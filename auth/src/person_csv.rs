@@ -0,0 +1,429 @@
+use crate::person::{Person, PersonField};
+use crate::search;
+use std::collections::HashMap;
+
+/// Columns of a CSV file of persons
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CsvColumn {
+    Id,
+    Field(PersonField),
+}
+
+impl CsvColumn {
+    /// Every column in the order they are exported
+    pub fn all() -> Vec<CsvColumn> {
+        std::iter::once(CsvColumn::Id)
+            .chain(
+                PersonField::ALL
+                    .iter()
+                    .map(|field| CsvColumn::Field(*field)),
+            )
+            .collect()
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CsvColumn::Id => "ID",
+            CsvColumn::Field(field) => field.label(),
+        }
+    }
+}
+
+/// Index of the file column read for each `CsvColumn`, columns missing
+/// from the mapping are left blank
+pub type ColumnMapping = HashMap<CsvColumn, usize>;
+
+/// Contents of a CSV file, the first line holds the headers
+#[derive(Debug, Clone, Default)]
+pub struct CsvTable {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+/// What importing a row would do
+#[derive(Debug, Clone, PartialEq)]
+pub enum RowAction {
+    Insert,
+    /// The ID column holds the id of an existing person
+    Update,
+    /// The row is skipped because of these errors
+    Reject(Vec<String>),
+}
+
+/// A row of the file as it would be imported
+#[derive(Debug, Clone)]
+pub struct PreviewRow {
+    /// Line of the row in the file, counting the headers as line 1
+    pub line: usize,
+    pub person: Person,
+    pub action: RowAction,
+    /// ID of the file matching no stored person, the row is inserted with
+    /// a new ID instead
+    pub unknown_id: Option<u32>,
+    /// The stored person with the ID of the row, which the row updates
+    pub stored: Option<Person>,
+}
+
+impl PreviewRow {
+    /// Fields the row changes in the stored person, none for new persons
+    pub fn changed_fields(&self) -> Vec<PersonField> {
+        let stored = match &self.stored {
+            Some(stored) => stored,
+            None => return Vec::new(),
+        };
+
+        PersonField::ALL
+            .iter()
+            .copied()
+            .filter(|field| stored.field_value(*field) != self.person.field_value(*field))
+            .collect()
+    }
+}
+
+/// First characters that make spreadsheets read a cell as a formula
+const FORMULA_STARTS: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+/// Whether `cell` is written with a leading `'`, so spreadsheets show it as
+/// text instead of running it as a formula. Cells already starting with `'`
+/// are escaped too when reading them back would drop it
+fn needs_escape(cell: &str) -> bool {
+    match cell.strip_prefix('\'') {
+        Some(rest) => needs_escape(rest),
+        None => cell.starts_with(&FORMULA_STARTS[..]),
+    }
+}
+
+fn escape(cell: String) -> String {
+    if needs_escape(&cell) {
+        format!("'{}", cell)
+    } else {
+        cell
+    }
+}
+
+fn unescape(cell: &str) -> &str {
+    match cell.strip_prefix('\'') {
+        Some(rest) if needs_escape(rest) => rest,
+        _ => cell,
+    }
+}
+
+/// Writes `persons` as CSV with a header line and every `CsvColumn`. Cells
+/// a spreadsheet would run as formulas get a leading `'`, which `parse`
+/// drops again
+pub fn export(persons: &[Person]) -> Result<String, String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let columns = CsvColumn::all();
+
+    writer
+        .write_record(columns.iter().map(|column| column.label()))
+        .map_err(|err| err.to_string())?;
+
+    for person in persons {
+        let record = columns.iter().map(|column| match column {
+            CsvColumn::Id => person.id.to_string(),
+            CsvColumn::Field(field) => escape(person.field_value(*field)),
+        });
+
+        writer.write_record(record).map_err(|err| err.to_string())?;
+    }
+
+    let bytes = writer.into_inner().map_err(|err| err.to_string())?;
+
+    String::from_utf8(bytes).map_err(|err| err.to_string())
+}
+
+/// Reads a CSV file, rows may have fewer or more values than headers
+pub fn parse(text: &str) -> Result<CsvTable, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(text.trim_start_matches('\u{feff}').as_bytes());
+
+    let headers = reader
+        .headers()
+        .map_err(|err| err.to_string())?
+        .iter()
+        .map(str::to_string)
+        .collect();
+    let rows = reader
+        .records()
+        .map(|record| {
+            record
+                .map(|record| {
+                    record
+                        .iter()
+                        .map(|cell| unescape(cell).to_string())
+                        .collect()
+                })
+                .map_err(|err| err.to_string())
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(CsvTable { headers, rows })
+}
+
+/// Maps each column to the first header with the same label, ignoring case,
+/// accents, spaces and underscores, so files exported by the app map fully
+pub fn guess_mapping(headers: &[String]) -> ColumnMapping {
+    let key = |text: &str| {
+        search::normalize(text, false)
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .collect::<String>()
+    };
+
+    CsvColumn::all()
+        .into_iter()
+        .filter_map(|column| {
+            headers
+                .iter()
+                .position(|header| key(header) == key(column.label()))
+                .map(|index| (column, index))
+        })
+        .collect()
+}
+
+/// Decides what importing each row of `table` would do, `stored` gives the
+/// stored person with an id, if any. Updates keep the stored value of the
+/// fields whose column is missing from the mapping or the row. Rows
+/// repeating the ID of an earlier row are rejected, so every person is
+/// updated at most once
+pub fn preview<F>(table: &CsvTable, mapping: &ColumnMapping, stored: F) -> Vec<PreviewRow>
+where
+    F: Fn(u32) -> Option<Person>,
+{
+    let mut lines_by_id: HashMap<u32, usize> = HashMap::new();
+
+    table
+        .rows
+        .iter()
+        .enumerate()
+        .map(|(index, row)| {
+            let line = index + 2;
+            let value = |column: CsvColumn| {
+                mapping
+                    .get(&column)
+                    .and_then(|index| row.get(*index))
+                    .cloned()
+            };
+
+            let mut id_errors = Vec::new();
+            let id = value(CsvColumn::Id).unwrap_or_default();
+            let id = if id.is_empty() {
+                None
+            } else {
+                id.parse::<u32>().map(Some).unwrap_or_else(|_| {
+                    id_errors.push("ID: Must be a whole number".to_string());
+                    None
+                })
+            };
+
+            if let Some(id) = id {
+                match lines_by_id.get(&id) {
+                    Some(first) => id_errors.push(format!("ID: Already on line {}", first)),
+                    None => {
+                        lines_by_id.insert(id, line);
+                    }
+                }
+            }
+
+            let existing = id.and_then(&stored);
+            let field_value = |field: PersonField| {
+                value(CsvColumn::Field(field))
+                    .or_else(|| existing.as_ref().map(|person| person.field_value(field)))
+                    .unwrap_or_default()
+            };
+
+            let errors: Vec<String> = Person::validate_values(field_value)
+                .err()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(field, message)| format!("{}: {}", field.label(), message))
+                .chain(id_errors)
+                .collect();
+
+            let action = if !errors.is_empty() {
+                RowAction::Reject(errors)
+            } else if existing.is_some() {
+                RowAction::Update
+            } else {
+                RowAction::Insert
+            };
+            let person_id = existing.as_ref().map_or(0, |person| person.id);
+
+            PreviewRow {
+                line,
+                person: Person::from_values(person_id, field_value),
+                unknown_id: id.filter(|_| existing.is_none()),
+                stored: existing,
+                action,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::person::Address;
+    use chrono::NaiveDate;
+
+    fn persons() -> Vec<Person> {
+        vec![
+            Person {
+                email: Some("esteban@example.com".to_string()),
+                phone: Some("+34 600 123 456".to_string()),
+                birth_date: NaiveDate::from_ymd_opt(1990, 5, 17),
+                address: Some(Address {
+                    street: "1, \"Main\" Street".to_string(),
+                    city: "Bogotá".to_string(),
+                    postal_code: "110111".to_string(),
+                    country: "Colombia".to_string(),
+                }),
+                ..Person::new(1, "Borai, Esteban")
+            },
+            Person::new(2, "June \"Junie\" O'Hara"),
+            Person::new(3, "Carlos\nMaría, \"Charlie\""),
+        ]
+    }
+
+    #[test]
+    fn export_writes_headers_and_quotes_values() {
+        let csv = export(&persons()).unwrap();
+        let mut lines = csv.lines();
+
+        assert_eq!(
+            lines.next(),
+            Some("ID,Name,Email,Phone,Birth Date,Street,City,Postal Code,Country")
+        );
+        assert_eq!(
+            lines.next(),
+            Some(concat!(
+                r#"1,"Borai, Esteban",esteban@example.com,'+34 600 123 456,1990-05-17,"#,
+                r#""1, ""Main"" Street",Bogotá,110111,Colombia"#
+            ))
+        );
+        assert_eq!(lines.next(), Some(r#"2,"June ""Junie"" O'Hara",,,,,,,"#));
+    }
+
+    #[test]
+    fn parse_reads_back_what_export_writes() {
+        let persons = persons();
+        let table = parse(&export(&persons).unwrap()).unwrap();
+        let labels: Vec<&str> = CsvColumn::all().iter().map(|c| c.label()).collect();
+
+        assert_eq!(table.headers, labels);
+        assert_eq!(table.rows.len(), persons.len());
+
+        let mapping = guess_mapping(&table.headers);
+        let stored = |id| persons.iter().find(|person| person.id == id).cloned();
+        let imported: Vec<Person> = preview(&table, &mapping, stored)
+            .into_iter()
+            .map(|row| {
+                assert_eq!(row.action, RowAction::Update);
+                row.person
+            })
+            .collect();
+
+        assert_eq!(imported, persons);
+    }
+
+    #[test]
+    fn export_escapes_formulas_and_parse_reads_them_back() {
+        let names = ["=1+2", "@SUM(A1)", "-5", "'=quoted", "'Tis", "O'Hara"];
+        let persons: Vec<Person> = names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| Person::new(index as u32 + 1, name))
+            .collect();
+        let csv = export(&persons).unwrap();
+        let exported: Vec<&str> = csv
+            .lines()
+            .skip(1)
+            .map(|line| line.split(',').nth(1).unwrap())
+            .collect();
+
+        assert_eq!(
+            exported,
+            vec!["'=1+2", "'@SUM(A1)", "'-5", "''=quoted", "'Tis", "O'Hara"]
+        );
+
+        let table = parse(&csv).unwrap();
+        let read: Vec<&str> = table.rows.iter().map(|row| row[1].as_str()).collect();
+
+        assert_eq!(read, names);
+    }
+
+    #[test]
+    fn parse_accepts_a_byte_order_mark_and_ragged_rows() {
+        let table = parse("\u{feff}ID,Name\n1, Ana ,extra\n2\n").unwrap();
+
+        assert_eq!(table.headers, vec!["ID", "Name"]);
+        assert_eq!(table.rows, vec![vec!["1", "Ana", "extra"], vec!["2"]]);
+    }
+
+    #[test]
+    fn preview_rejects_repeated_ids_and_flags_unknown_ones() {
+        let table = parse("ID,Name\n2,June\n,Ana\n9,Nina\n2,Junie\n").unwrap();
+        let june = |id| Some(Person::new(2, "June")).filter(|_| id == 2);
+        let rows = preview(&table, &guess_mapping(&table.headers), june);
+        let summary: Vec<(usize, &RowAction, Option<u32>)> = rows
+            .iter()
+            .map(|row| (row.line, &row.action, row.unknown_id))
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                (2, &RowAction::Update, None),
+                (3, &RowAction::Insert, None),
+                (4, &RowAction::Insert, Some(9)),
+                (
+                    5,
+                    &RowAction::Reject(vec!["ID: Already on line 2".to_string()]),
+                    None
+                ),
+            ]
+        );
+        assert_eq!(rows[2].person.id, 0);
+    }
+
+    #[test]
+    fn updates_keep_the_fields_missing_from_the_file() {
+        let stored = persons().remove(0);
+        let table = parse("ID,Name,Phone\n1,Esteban Borai,\n1,Borai\n").unwrap();
+        let mapping = guess_mapping(&table.headers);
+        let rows = preview(&table, &mapping, |_| Some(stored.clone()));
+        let row = &rows[0];
+
+        assert_eq!(row.action, RowAction::Update);
+        assert_eq!(row.person.email, stored.email);
+        assert_eq!(row.person.address, stored.address);
+        assert_eq!(row.person.birth_date, stored.birth_date);
+        assert_eq!(row.person.phone, None);
+        assert_eq!(
+            row.changed_fields(),
+            vec![PersonField::Name, PersonField::Phone]
+        );
+
+        // The second row is rejected as a repeated ID, but the missing
+        // phone of its short row is kept as stored
+        assert_eq!(rows[1].person.phone, stored.phone);
+    }
+
+    #[test]
+    fn guess_mapping_ignores_case_accents_and_separators() {
+        let headers: Vec<String> = ["name", "BIRTH_DATE", "Códe", "postal code"]
+            .iter()
+            .map(|header| header.to_string())
+            .collect();
+        let mapping = guess_mapping(&headers);
+        let index = |field| mapping.get(&CsvColumn::Field(field)).copied();
+
+        assert_eq!(index(PersonField::Name), Some(0));
+        assert_eq!(index(PersonField::BirthDate), Some(1));
+        assert_eq!(index(PersonField::PostalCode), Some(3));
+        assert_eq!(mapping.get(&CsvColumn::Id), None);
+    }
+}
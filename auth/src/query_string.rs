@@ -0,0 +1,72 @@
+/// Escapes `value` for a query string, every byte but the unreserved
+/// characters of URLs is percent-encoded
+pub fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Unescapes a value of a query string, `+` standing for a space. `None`
+/// when an escape is cut short or invalid, or the value isn't UTF-8
+pub fn decode(value: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut input = value.bytes();
+
+    while let Some(byte) = input.next() {
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex = [input.next()?, input.next()?];
+                let hex = std::str::from_utf8(&hex).ok()?;
+
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            }
+            byte => bytes.push(byte),
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_escapes_reserved_and_non_ascii_characters() {
+        assert_eq!(encode("Ana María"), "Ana%20Mar%C3%ADa");
+        assert_eq!(encode("a&b=c+d/e?"), "a%26b%3Dc%2Bd%2Fe%3F");
+        assert_eq!(encode("Az09-_.~"), "Az09-_.~");
+        assert_eq!(encode(""), "");
+    }
+
+    #[test]
+    fn decode_reverses_encode() {
+        for value in &["José Martín", "100% & more", "a+b", "line\nbreak", "日本"] {
+            assert_eq!(decode(&encode(value)).as_deref(), Some(*value));
+        }
+    }
+
+    #[test]
+    fn decode_reads_plus_as_space_and_either_case() {
+        assert_eq!(decode("Ana+Mar%c3%ADa").as_deref(), Some("Ana María"));
+        assert_eq!(
+            decode("http://localhost:8080").as_deref(),
+            Some("http://localhost:8080")
+        );
+    }
+
+    #[test]
+    fn decode_rejects_invalid_escapes() {
+        assert_eq!(decode("%"), None);
+        assert_eq!(decode("%4"), None);
+        assert_eq!(decode("%zz"), None);
+        assert_eq!(decode("%C3"), None);
+    }
+}
@@ -0,0 +1,85 @@
+use yew::Callback;
+
+use crate::events::WindowListener;
+
+/// Pages of the app as kept in the URL hash, like `#/persons/3`, so the
+/// back button, bookmarks and reloads work with a static file server
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Route {
+    Login,
+    PersonsList,
+    NewPerson,
+    ImportPersons,
+    Person(u32),
+    Trash,
+    UsersList,
+    AuditLog,
+    Backup,
+}
+
+impl Route {
+    /// Parses the hash of the URL, with or without the leading `#`
+    pub fn from_hash(hash: &str) -> Option<Route> {
+        let path = hash.trim_start_matches('#').trim_end_matches('/');
+        let segments: Vec<&str> = path.split('/').skip(1).collect();
+
+        match segments.as_slice() {
+            ["login"] => Some(Route::Login),
+            ["persons"] => Some(Route::PersonsList),
+            ["persons", "new"] => Some(Route::NewPerson),
+            ["persons", "import"] => Some(Route::ImportPersons),
+            ["persons", id] => id.parse().ok().map(Route::Person),
+            ["trash"] => Some(Route::Trash),
+            ["users"] => Some(Route::UsersList),
+            ["audit"] => Some(Route::AuditLog),
+            ["backup"] => Some(Route::Backup),
+            _ => None,
+        }
+    }
+
+    pub fn to_hash(self) -> String {
+        match self {
+            Route::Login => "#/login".to_string(),
+            Route::PersonsList => "#/persons".to_string(),
+            Route::NewPerson => "#/persons/new".to_string(),
+            Route::ImportPersons => "#/persons/import".to_string(),
+            Route::Person(id) => format!("#/persons/{}", id),
+            Route::Trash => "#/trash".to_string(),
+            Route::UsersList => "#/users".to_string(),
+            Route::AuditLog => "#/audit".to_string(),
+            Route::Backup => "#/backup".to_string(),
+        }
+    }
+
+    pub fn requires_login(self) -> bool {
+        self != Route::Login
+    }
+}
+
+/// Route of the current URL, `None` when the hash is empty or unknown
+pub fn current() -> Option<Route> {
+    web_sys::window()
+        .and_then(|window| window.location().hash().ok())
+        .and_then(|hash| Route::from_hash(&hash))
+}
+
+/// Adds `route` to the browser history, listeners are notified of it
+pub fn push(route: Route) {
+    if let Some(window) = web_sys::window() {
+        let _ = window.location().set_hash(&route.to_hash());
+    }
+}
+
+/// Replaces the current history entry with `route`, so going back skips
+/// the replaced page, listeners are notified of it
+pub fn replace(route: Route) {
+    if let Some(window) = web_sys::window() {
+        let _ = window.location().replace(&route.to_hash());
+    }
+}
+
+/// Notifies the route of the URL every time the hash changes until the
+/// listener is dropped
+pub fn listen(callback: Callback<Option<Route>>) -> WindowListener {
+    WindowListener::new("hashchange", callback.reform(|_| current()))
+}
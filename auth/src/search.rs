@@ -0,0 +1,225 @@
+use caseless::default_case_fold_str;
+use std::ops::Range;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Strips diacritics from `text` and, unless `match_case` is set, applies
+/// Unicode case folding, so "José" and "JOSE" both become "jose"
+pub fn normalize(text: &str, match_case: bool) -> String {
+    let stripped: String = text.nfd().filter(|c| !is_combining_mark(*c)).collect();
+
+    if match_case {
+        stripped.nfc().collect()
+    } else {
+        default_case_fold_str(&stripped)
+    }
+}
+
+/// Byte ranges of `text` matching `needle` once both are normalized, used
+/// to highlight the matches of a search in the original text
+pub fn find_matches(text: &str, needle: &str, match_case: bool) -> Vec<Range<usize>> {
+    let needle = normalize(needle, match_case);
+
+    if needle.is_empty() {
+        return Vec::new();
+    }
+
+    // Normalizing char by char keeps track of the original char each byte
+    // of the normalized text comes from
+    let mut normalized = String::new();
+    let mut origins: Vec<Range<usize>> = Vec::new();
+
+    for (index, c) in text.char_indices() {
+        let piece = normalize(c.encode_utf8(&mut [0; 4]), match_case);
+
+        origins.extend(std::iter::repeat_n(
+            index..index + c.len_utf8(),
+            piece.len(),
+        ));
+        normalized.push_str(&piece);
+    }
+
+    let mut matches: Vec<Range<usize>> = Vec::new();
+
+    for (start, found) in normalized.match_indices(&needle) {
+        let range = origins[start].start..origins[start + found.len() - 1].end;
+
+        match matches.last_mut() {
+            Some(last) if last.end >= range.start => last.end = last.end.max(range.end),
+            _ => matches.push(range),
+        }
+    }
+
+    matches
+}
+
+/// Minimum score a person needs to show up in a fuzzy search
+pub const FUZZY_THRESHOLD: f32 = 0.6;
+
+/// Score given to words that sound alike but are spelled differently
+const PHONETIC_SCORE: f32 = 0.75;
+
+/// Ranks how close `text` is to `query` between `0.0` and `1.0`.
+///
+/// Every word of the query is paired with its closest word in the text,
+/// by edit distance or by sounding alike, and the scores are averaged.
+pub fn fuzzy_score(text: &str, query: &str) -> f32 {
+    let text = normalize(text, false);
+    let query = normalize(query, false);
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let query_words: Vec<&str> = query.split_whitespace().collect();
+
+    if query_words.is_empty() || words.is_empty() {
+        return 0.0;
+    }
+
+    let total: f32 = query_words
+        .iter()
+        .map(|query_word| {
+            words
+                .iter()
+                .map(|word| word_score(word, query_word))
+                .fold(0.0, f32::max)
+        })
+        .sum();
+
+    total / query_words.len() as f32
+}
+
+/// Words sound alike when their Soundex codes match, Metaphone is not used
+fn word_score(word: &str, query_word: &str) -> f32 {
+    if word.contains(query_word) {
+        return 1.0;
+    }
+
+    let max_len = word.chars().count().max(query_word.chars().count());
+    let similarity = 1.0 - levenshtein(word, query_word) as f32 / max_len as f32;
+
+    match (soundex(word), soundex(query_word)) {
+        (Some(a), Some(b)) if a == b => similarity.max(PHONETIC_SCORE),
+        _ => similarity,
+    }
+}
+
+/// Number of single character insertions, deletions or substitutions
+/// needed to turn `a` into `b`
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + if a_char == *b_char { 0 } else { 1 };
+
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// American Soundex code of `word`, `None` when it has no ASCII letters
+pub fn soundex(word: &str) -> Option<String> {
+    fn digit(c: char) -> Option<char> {
+        match c {
+            'B' | 'F' | 'P' | 'V' => Some('1'),
+            'C' | 'G' | 'J' | 'K' | 'Q' | 'S' | 'X' | 'Z' => Some('2'),
+            'D' | 'T' => Some('3'),
+            'L' => Some('4'),
+            'M' | 'N' => Some('5'),
+            'R' => Some('6'),
+            _ => None,
+        }
+    }
+
+    let mut letters = word
+        .chars()
+        .filter(char::is_ascii_alphabetic)
+        .map(|c| c.to_ascii_uppercase());
+    let first = letters.next()?;
+    let mut code = first.to_string();
+    let mut last = digit(first);
+
+    for letter in letters {
+        let current = digit(letter);
+
+        if let Some(d) = current {
+            if current != last {
+                code.push(d);
+            }
+        }
+
+        // Letters separated by H or W are coded once, vowels split them
+        if letter != 'H' && letter != 'W' {
+            last = current;
+        }
+
+        if code.len() == 4 {
+            break;
+        }
+    }
+
+    while code.len() < 4 {
+        code.push('0');
+    }
+
+    Some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_strips_accents_and_folds_case() {
+        assert_eq!(normalize("José", false), "jose");
+        assert_eq!(normalize("JOSÉ", false), "jose");
+        assert_eq!(normalize("José", true), "Jose");
+        assert_eq!(normalize("Straße", false), "strasse");
+    }
+
+    #[test]
+    fn find_matches_returns_original_byte_ranges() {
+        assert_eq!(find_matches("José Martín", "jose", false), vec![0..5]);
+        assert_eq!(find_matches("José Martín", "TIN", false), vec![9..13]);
+        assert!(find_matches("José Martín", "TIN", true).is_empty());
+        assert_eq!(find_matches("Ana Anaya", "ana", false), vec![0..3, 4..7]);
+        assert!(find_matches("Ana", " ", false).is_empty());
+        assert!(find_matches("Ana", "", false).is_empty());
+    }
+
+    #[test]
+    fn levenshtein_counts_edits() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("flaw", "lawn"), 2);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("abc", "abc"), 0);
+        assert_eq!(levenshtein("josé", "jose"), 1);
+    }
+
+    #[test]
+    fn soundex_codes() {
+        assert_eq!(soundex("Robert").as_deref(), Some("R163"));
+        assert_eq!(soundex("Rupert").as_deref(), Some("R163"));
+        assert_eq!(soundex("Ashcraft").as_deref(), Some("A261"));
+        assert_eq!(soundex("Tymczak").as_deref(), Some("T522"));
+        assert_eq!(soundex("Pfister").as_deref(), Some("P236"));
+        assert_eq!(soundex("Lee").as_deref(), Some("L000"));
+        assert_eq!(soundex("123"), None);
+    }
+
+    #[test]
+    fn fuzzy_score_ranks_spelling_and_sound() {
+        assert_eq!(fuzzy_score("Esteban", "esteban"), 1.0);
+        assert_eq!(fuzzy_score("José Martín", "martin jose"), 1.0);
+        assert!(fuzzy_score("Robert Smith", "Rupert") >= PHONETIC_SCORE);
+        assert!(fuzzy_score("Carlos", "Karlos") >= FUZZY_THRESHOLD);
+        assert!(fuzzy_score("June", "Carlos") < FUZZY_THRESHOLD);
+        assert_eq!(fuzzy_score("June", ""), 0.0);
+        assert_eq!(fuzzy_score("", "June"), 0.0);
+    }
+}
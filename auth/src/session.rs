@@ -0,0 +1,111 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use web_sys::Storage;
+
+/// Key used to keep the `Session` in the browser's `localStorage`
+const STORAGE_KEY: &str = "yew-app-samples.auth.session";
+
+/// Time a session lasts since logging in, whatever the activity
+pub const SESSION_LIFETIME: Duration = Duration::hours(8);
+
+/// Time without any activity after which the user is logged out
+pub const IDLE_TIMEOUT: Duration = Duration::minutes(15);
+
+/// Time before the automatic logout during which the user is warned
+pub const LOGOUT_WARNING: Duration = Duration::minutes(1);
+
+/// Activity is saved at most once in this time, so moving the mouse
+/// doesn't write to the storage on every event
+const ACTIVITY_RESOLUTION: Duration = Duration::seconds(5);
+
+/// A logged in user kept in `localStorage`, so reloading the page doesn't
+/// log the user out.
+///
+/// The session only keeps the username, privileges are read again from the
+/// database when the session is restored.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub token: String,
+    pub username: String,
+    pub expires_at: DateTime<Utc>,
+    pub last_activity: DateTime<Utc>,
+}
+
+impl Session {
+    /// Starts and saves a new session for `username`, with the token it
+    /// was handed when logging in
+    pub fn start(username: &str, token: String) -> Self {
+        let now = Utc::now();
+        let session = Self {
+            token,
+            username: username.to_string(),
+            expires_at: now + SESSION_LIFETIME,
+            last_activity: now,
+        };
+
+        session.save();
+
+        session
+    }
+
+    /// Restores the saved session, an expired one is removed instead
+    pub fn restore() -> Option<Self> {
+        let session = storage()
+            .and_then(|storage| storage.get_item(STORAGE_KEY).ok().flatten())
+            .and_then(|json| serde_json::from_str::<Session>(&json).ok());
+
+        match session {
+            Some(session) if !session.is_expired(Utc::now()) => Some(session),
+            _ => {
+                Session::clear();
+                None
+            }
+        }
+    }
+
+    /// Removes the saved session, logging the user out
+    pub fn clear() {
+        if let Some(storage) = storage() {
+            let _ = storage.remove_item(STORAGE_KEY);
+        }
+    }
+
+    fn save(&self) {
+        let json = serde_json::to_string(self).expect("Unable to serialize the session");
+
+        if let Some(storage) = storage() {
+            if storage.set_item(STORAGE_KEY, &json).is_err() {
+                web_sys::console::error_1(&"Unable to save the session".into());
+            }
+        }
+    }
+
+    /// Records activity of the user, postponing the idle logout
+    pub fn touch(&mut self) {
+        let now = Utc::now();
+
+        if now - self.last_activity >= ACTIVITY_RESOLUTION {
+            self.last_activity = now;
+            self.save();
+        }
+    }
+
+    /// Whether the session ends because of inactivity rather than its
+    /// lifetime
+    pub fn ends_idle(&self) -> bool {
+        self.last_activity + IDLE_TIMEOUT < self.expires_at
+    }
+
+    /// Moment the user is logged out unless there is some activity
+    pub fn deadline(&self) -> DateTime<Utc> {
+        self.expires_at.min(self.last_activity + IDLE_TIMEOUT)
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        now >= self.deadline()
+    }
+}
+
+fn storage() -> Option<Storage> {
+    web_sys::window().and_then(|window| window.local_storage().ok().flatten())
+}
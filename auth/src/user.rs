@@ -1,32 +1,322 @@
-#[derive(Debug, Clone, PartialEq)]
+use pbkdf2::pbkdf2_hmac;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+
+/// Number of PBKDF2 rounds used for new password hashes
+const PBKDF2_ITERATIONS: u32 = 100_000;
+const SALT_LENGTH: usize = 16;
+const HASH_LENGTH: usize = 32;
+
+/// A single operation over the data, users are granted privileges through
+/// their roles
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum DbPrivilege {
+    CanRead,
+    CanCreate,
+    CanUpdate,
+    CanDelete,
+    CanExport,
+    CanAudit,
+    CanManageUsers,
+}
+
+impl DbPrivilege {
+    pub const ALL: [DbPrivilege; 7] = [
+        DbPrivilege::CanRead,
+        DbPrivilege::CanCreate,
+        DbPrivilege::CanUpdate,
+        DbPrivilege::CanDelete,
+        DbPrivilege::CanExport,
+        DbPrivilege::CanAudit,
+        DbPrivilege::CanManageUsers,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DbPrivilege::CanRead => "Read",
+            DbPrivilege::CanCreate => "Create",
+            DbPrivilege::CanUpdate => "Update",
+            DbPrivilege::CanDelete => "Delete",
+            DbPrivilege::CanExport => "Export",
+            DbPrivilege::CanAudit => "Audit",
+            DbPrivilege::CanManageUsers => "Manage Users",
+        }
+    }
+
+    /// What the privilege allows, as in "You are not allowed to ..."
+    pub fn action(self) -> &'static str {
+        match self {
+            DbPrivilege::CanRead => "read persons",
+            DbPrivilege::CanCreate => "create persons",
+            DbPrivilege::CanUpdate => "update persons",
+            DbPrivilege::CanDelete => "delete persons",
+            DbPrivilege::CanExport => "export persons",
+            DbPrivilege::CanAudit => "view the audit log",
+            DbPrivilege::CanManageUsers => "manage users",
+        }
+    }
+}
+
+/// A named set of privileges assigned to users
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Role {
+    Viewer,
+    Editor,
+    Manager,
+    Administrator,
+}
+
+impl Role {
+    pub const ALL: [Role; 4] = [
+        Role::Viewer,
+        Role::Editor,
+        Role::Manager,
+        Role::Administrator,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Role::Viewer => "Viewer",
+            Role::Editor => "Editor",
+            Role::Manager => "Manager",
+            Role::Administrator => "Administrator",
+        }
+    }
+
+    pub fn privileges(self) -> &'static [DbPrivilege] {
+        match self {
+            Role::Viewer => &[DbPrivilege::CanRead],
+            Role::Editor => &[
+                DbPrivilege::CanRead,
+                DbPrivilege::CanCreate,
+                DbPrivilege::CanUpdate,
+            ],
+            Role::Manager => &[
+                DbPrivilege::CanRead,
+                DbPrivilege::CanCreate,
+                DbPrivilege::CanUpdate,
+                DbPrivilege::CanDelete,
+                DbPrivilege::CanExport,
+            ],
+            Role::Administrator => &DbPrivilege::ALL,
+        }
+    }
+}
+
+/// Privileges stored by versions without roles
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+enum LegacyPrivilege {
     CanRead,
     CanWrite,
+    CanManageUsers,
 }
 
-#[derive(Debug, Clone)]
-pub struct User {
-    pub username: String,
-    pub password: String,
-    pub privileges: Vec<DbPrivilege>,
+/// Password of a serialized `User`, versions before hashing stored it as
+/// plain text
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredPassword {
+    Hash(PasswordHash),
+    Plain(String),
+}
+
+/// Unchecked shape of a serialized `User`, either with roles or with the
+/// privileges of older versions
+#[derive(Deserialize)]
+struct StoredUser {
+    username: String,
+    password: StoredPassword,
+    #[serde(default)]
+    roles: Vec<Role>,
+    #[serde(default)]
+    privileges: Vec<LegacyPrivilege>,
+    #[serde(default)]
+    disabled: bool,
 }
 
-impl Default for User {
-    fn default() -> Self {
+impl From<StoredUser> for User {
+    /// Users without roles get the narrowest role covering their old
+    /// privileges, plain text passwords are hashed
+    fn from(stored: StoredUser) -> Self {
+        let mut roles = stored.roles;
+
+        if roles.is_empty() {
+            let privileges = stored.privileges;
+            let has = |privilege| privileges.contains(&privilege);
+
+            if has(LegacyPrivilege::CanManageUsers) {
+                roles.push(Role::Administrator);
+            } else if has(LegacyPrivilege::CanWrite) {
+                roles.push(Role::Manager);
+            } else if has(LegacyPrivilege::CanRead) {
+                roles.push(Role::Viewer);
+            }
+        }
+
+        Self {
+            username: stored.username,
+            password: match stored.password {
+                StoredPassword::Hash(hash) => hash,
+                StoredPassword::Plain(password) => PasswordHash::new(&password),
+            },
+            roles,
+            disabled: stored.disabled,
+        }
+    }
+}
+
+/// Salted PBKDF2-HMAC-SHA256 hash of a password, the salt and hash are
+/// kept hex encoded so the value serializes as plain JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PasswordHash {
+    salt: String,
+    hash: String,
+    iterations: u32,
+}
+
+impl PasswordHash {
+    /// Hashes `password` with a freshly generated random salt
+    pub fn new(password: &str) -> Self {
+        let mut salt = [0u8; SALT_LENGTH];
+
+        getrandom::getrandom(&mut salt).expect("Unable to generate a password salt");
+
+        Self {
+            salt: hex::encode(salt),
+            hash: hex::encode(PasswordHash::derive(password, &salt, PBKDF2_ITERATIONS)),
+            iterations: PBKDF2_ITERATIONS,
+        }
+    }
+
+    /// Builds a `PasswordHash` from an already computed hex encoded salt
+    /// and hash
+    pub fn from_hex(salt: &str, hash: &str, iterations: u32) -> Self {
         Self {
-            username: String::default(),
-            password: String::default(),
-            privileges: Vec::new(),
+            salt: salt.to_string(),
+            hash: hash.to_string(),
+            iterations,
         }
     }
+
+    /// Checks `password` against the stored hash in constant time
+    pub fn verify(&self, password: &str) -> bool {
+        let (salt, expected) = match (hex::decode(&self.salt), hex::decode(&self.hash)) {
+            (Ok(salt), Ok(expected)) => (salt, expected),
+            _ => return false,
+        };
+        let actual = PasswordHash::derive(password, &salt, self.iterations);
+
+        actual.ct_eq(&expected).into()
+    }
+
+    /// Spends as long as `verify` hashing `password`, for users that don't
+    /// exist, so they can't be told apart from wrong passwords by timing
+    pub fn verify_unknown(password: &str) {
+        PasswordHash::derive(password, &[0u8; SALT_LENGTH], PBKDF2_ITERATIONS);
+    }
+
+    fn derive(password: &str, salt: &[u8], iterations: u32) -> Vec<u8> {
+        let mut hash = vec![0u8; HASH_LENGTH];
+
+        pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, iterations, &mut hash);
+
+        hash
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "StoredUser")]
+pub struct User {
+    pub username: String,
+    pub password: PasswordHash,
+    pub roles: Vec<Role>,
+    /// Disabled users are kept but can't log in
+    #[serde(default)]
+    pub disabled: bool,
 }
 
 impl User {
-    pub fn new(username: &str, password: &str, privileges: Vec<DbPrivilege>) -> Self {
+    pub fn new(username: &str, password: PasswordHash, roles: Vec<Role>) -> Self {
         Self {
             username: username.to_string(),
-            password: password.to_string(),
-            privileges,
+            password,
+            roles,
+            disabled: false,
         }
     }
+
+    pub fn verify_password(&self, password: &str) -> bool {
+        self.password.verify(password)
+    }
+
+    pub fn has_role(&self, role: Role) -> bool {
+        self.roles.contains(&role)
+    }
+
+    pub fn has_privilege(&self, privilege: DbPrivilege) -> bool {
+        self.roles
+            .iter()
+            .any(|role| role.privileges().contains(&privilege))
+    }
+
+    /// Every privilege granted by any of the roles of the user
+    pub fn privileges(&self) -> Vec<DbPrivilege> {
+        DbPrivilege::ALL
+            .iter()
+            .copied()
+            .filter(|privilege| self.has_privilege(*privilege))
+            .collect()
+    }
+}
+
+/// A logged in `User` without any credentials, this is what is handed
+/// around the application once the password was verified
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuthenticatedUser {
+    pub username: String,
+    pub privileges: Vec<DbPrivilege>,
+}
+
+impl AuthenticatedUser {
+    pub fn has_privilege(&self, privilege: DbPrivilege) -> bool {
+        self.privileges.contains(&privilege)
+    }
+}
+
+impl From<&User> for AuthenticatedUser {
+    fn from(user: &User) -> Self {
+        Self {
+            username: user.username.clone(),
+            privileges: user.privileges(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_text_passwords_are_hashed_on_load() {
+        let json = r#"{"username":"root","password":"root","privileges":["CanRead","CanWrite"]}"#;
+        let user: User = serde_json::from_str(json).unwrap();
+
+        assert!(user.verify_password("root"));
+        assert!(!user.verify_password("john"));
+        assert_eq!(user.roles, vec![Role::Manager]);
+        assert!(!serde_json::to_string(&user)
+            .unwrap()
+            .contains(r#""password":"root""#));
+    }
+
+    #[test]
+    fn hashed_passwords_are_kept() {
+        let user = User::new("john", PasswordHash::new("secret"), vec![Role::Viewer]);
+        let json = serde_json::to_string(&user).unwrap();
+
+        assert_eq!(serde_json::from_str::<User>(&json).unwrap(), user);
+    }
 }
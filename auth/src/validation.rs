@@ -0,0 +1,100 @@
+use chrono::{NaiveDate, Utc};
+
+/// Format used for dates in forms and when validating
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// A declarative check over the text of a field.
+///
+/// Every rule but `Required` accepts an empty value, so optional fields
+/// only need to be valid when filled in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rule {
+    Required,
+    MaxLength(usize),
+    Email,
+    Phone,
+    /// A date not before January 1st of the given year nor after today
+    PastDate {
+        min_year: i32,
+    },
+}
+
+impl Rule {
+    pub fn check(self, value: &str) -> Result<(), String> {
+        let value = value.trim();
+
+        if value.is_empty() {
+            return match self {
+                Rule::Required => Err("This field is required".to_string()),
+                _ => Ok(()),
+            };
+        }
+
+        match self {
+            Rule::Required => Ok(()),
+            Rule::MaxLength(max) => {
+                if value.chars().count() > max {
+                    Err(format!("Must be at most {} characters long", max))
+                } else {
+                    Ok(())
+                }
+            }
+            Rule::Email => {
+                if is_email(value) {
+                    Ok(())
+                } else {
+                    Err("Must be a valid email address".to_string())
+                }
+            }
+            Rule::Phone => {
+                if is_phone(value) {
+                    Ok(())
+                } else {
+                    Err("Must be a valid phone number".to_string())
+                }
+            }
+            Rule::PastDate { min_year } => {
+                let date = NaiveDate::parse_from_str(value, DATE_FORMAT)
+                    .map_err(|_| "Must be a date formatted as YYYY-MM-DD".to_string())?;
+                let today = Utc::now().date_naive();
+
+                if date > today {
+                    Err("Can't be in the future".to_string())
+                } else if NaiveDate::from_ymd_opt(min_year, 1, 1).is_some_and(|min| date < min) {
+                    Err(format!("Can't be before {}", min_year))
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
+/// Runs `rules` in order and returns the message of the first one failing
+pub fn validate(value: &str, rules: &[Rule]) -> Result<(), String> {
+    rules.iter().try_for_each(|rule| rule.check(value))
+}
+
+fn is_email(value: &str) -> bool {
+    let mut parts = value.split('@');
+
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(local), Some(domain), None) => {
+            !local.is_empty()
+                && !value.chars().any(char::is_whitespace)
+                && domain.contains('.')
+                && domain.split('.').all(|label| !label.is_empty())
+        }
+        _ => false,
+    }
+}
+
+fn is_phone(value: &str) -> bool {
+    let digits = value.chars().filter(char::is_ascii_digit).count();
+    let allowed = value
+        .chars()
+        .enumerate()
+        .all(|(index, c)| c.is_ascii_digit() || " -().".contains(c) || (c == '+' && index == 0));
+
+    allowed && (7..=15).contains(&digits)
+}
@@ -0,0 +1,670 @@
+use auth::database::{AuthStore, DbError, PersonStore, PersonsQuery, PersonsSort, SortColumn};
+use auth::person::Person;
+use auth::query_string::decode;
+use auth::user::AuthenticatedUser;
+use serde::{Deserialize, Serialize};
+use std::io::{Cursor, Read};
+use std::thread;
+use tiny_http::{Header, Method, Request, Response};
+
+use crate::config::Mock;
+use crate::file_store::FileStore;
+use crate::static_files::StaticFiles;
+
+type Reply = Response<Cursor<Vec<u8>>>;
+
+/// Largest body a request may send, a person takes far less
+const MAX_BODY_LENGTH: u64 = 64 * 1024;
+
+/// A failed request, sent as `{"error": message, "cause": err}` where
+/// `cause` is the `DbError` of the `Database`, if it failed there
+#[derive(Debug)]
+struct ApiError {
+    status: u16,
+    message: String,
+    cause: Option<DbError>,
+}
+
+impl ApiError {
+    fn new(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            message: message.to_string(),
+            cause: None,
+        }
+    }
+}
+
+impl From<DbError> for ApiError {
+    fn from(err: DbError) -> Self {
+        let status = match err {
+            DbError::NotFound(_) => 404,
+            DbError::Duplicate(_) | DbError::Conflict(_) => 409,
+            DbError::Validation(_) => 422,
+            DbError::Authentication(_) => 401,
+            DbError::PermissionDenied(_) => 403,
+            DbError::Storage(_) => 500,
+        };
+
+        Self {
+            status,
+            message: err.to_string(),
+            cause: Some(err),
+        }
+    }
+}
+
+impl From<std::io::Error> for ApiError {
+    fn from(err: std::io::Error) -> Self {
+        Self {
+            status: 500,
+            message: format!("Storage error: {}", err),
+            cause: None,
+        }
+    }
+}
+
+type ApiResult = Result<Reply, ApiError>;
+
+#[derive(Deserialize)]
+struct Credentials {
+    username: String,
+    password: String,
+}
+
+/// Body of `PUT /persons/:id`, the version that was read is taken from the
+/// `If-Match` header, or else from the body, but never defaults
+#[derive(Deserialize)]
+struct PersonUpdate {
+    version: Option<u32>,
+    #[serde(flatten)]
+    person: Person,
+}
+
+#[derive(Serialize)]
+struct Inserted {
+    id: u32,
+}
+
+/// The REST API over the persons of a `FileStore`, every other `GET`
+/// request is answered with the static files of the app
+pub struct Api {
+    store: FileStore,
+    static_files: StaticFiles,
+    mock: Mock,
+    /// API requests received, to fail every `mock.fail_every`th one
+    api_requests: u32,
+}
+
+impl Api {
+    pub fn new(store: FileStore, static_files: StaticFiles, mock: Mock) -> Self {
+        Self {
+            store,
+            static_files,
+            mock,
+            api_requests: 0,
+        }
+    }
+
+    pub fn handle(&mut self, mut request: Request) {
+        let reply = self.answer(&mut request);
+
+        if let Err(err) = request.respond(reply) {
+            eprintln!("Unable to answer a request: {}", err);
+        }
+    }
+
+    /// The reply to `request`, with the CORS headers every reply carries
+    fn answer(&mut self, request: &mut Request) -> Reply {
+        // Nobody is acting on the data until the request shows a token
+        self.store.database_mut().release();
+
+        let reply = if is_api(request) && !self.mock_answers() {
+            Err(ApiError::new(503, "The mock server failed the request"))
+        } else {
+            self.route(request)
+        };
+        let reply = reply.unwrap_or_else(|err| {
+            json(
+                err.status,
+                &serde_json::json!({ "error": err.message, "cause": err.cause }),
+            )
+        });
+
+        reply
+            .with_header(header("Access-Control-Allow-Origin", "*"))
+            .with_header(header(
+                "Access-Control-Allow-Methods",
+                "GET, POST, PUT, DELETE, OPTIONS",
+            ))
+            .with_header(header(
+                "Access-Control-Allow-Headers",
+                "Authorization, Content-Type, If-Match",
+            ))
+    }
+
+    /// Waits as `mock` says before an API request, `false` when the request
+    /// must fail instead of being handled
+    fn mock_answers(&mut self) -> bool {
+        self.api_requests += 1;
+
+        if !self.mock.delay.is_zero() {
+            thread::sleep(self.mock.delay);
+        }
+
+        match self.mock.fail_every {
+            Some(every) => !self.api_requests.is_multiple_of(every),
+            None => true,
+        }
+    }
+
+    fn route(&mut self, request: &mut Request) -> ApiResult {
+        let url = request.url().to_string();
+        let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        let method = request.method().clone();
+
+        match (&method, segments.as_slice()) {
+            (Method::Options, _) => Ok(Response::from_data(Vec::new()).with_status_code(204)),
+            (Method::Post, ["login"]) => self.log_in(request),
+            (Method::Post, ["logout"]) => self.log_out(request),
+            (Method::Get, ["session"]) => {
+                let user = self.act_as(request)?;
+
+                Ok(json(200, &user))
+            }
+            (Method::Get, ["persons"]) => {
+                self.act_as(request)?;
+
+                let query = persons_query(query)?;
+
+                Ok(json(200, &self.store.database().query_persons(&query)))
+            }
+            (Method::Post, ["persons"]) => {
+                self.act_as(request)?;
+
+                let person: Person = read_json(request)?;
+                let id = self.store.change(|database| database.insert(person))?;
+
+                Ok(json(201, &Inserted { id }))
+            }
+            (Method::Get, ["persons", id]) => {
+                self.act_as(request)?;
+
+                let id = parse_id(id)?;
+
+                match self.store.database().get_person_by_id(id) {
+                    Some(person) => Ok(json(200, &person)),
+                    None => Err(DbError::NotFound(format!("Person with ID {}", id)).into()),
+                }
+            }
+            (Method::Put, ["persons", id]) => {
+                self.act_as(request)?;
+
+                let id = parse_id(id)?;
+                let version = if_match(request)?;
+                let PersonUpdate {
+                    person,
+                    version: body_version,
+                } = read_json(request)?;
+                let version = version.or(body_version).ok_or_else(|| {
+                    ApiError::new(400, "Send the version of the person as If-Match")
+                })?;
+                let person = Person {
+                    id,
+                    version,
+                    ..person
+                };
+
+                self.store.change(|database| database.update(person))?;
+
+                match self.store.database().get_person_by_id(id) {
+                    Some(person) => Ok(json(200, &person)),
+                    None => Err(DbError::NotFound(format!("Person with ID {}", id)).into()),
+                }
+            }
+            (Method::Delete, ["persons", id]) => {
+                self.act_as(request)?;
+
+                let id = parse_id(id)?;
+                let version = if_match(request)?;
+
+                self.store.change(|database| database.delete(id, version))?;
+
+                Ok(Response::from_data(Vec::new()).with_status_code(204))
+            }
+            (Method::Get, _) => {
+                match self.static_files.read(path) {
+                    Some((content, content_type)) => Ok(Response::from_data(content)
+                        .with_header(header("Content-Type", content_type))),
+                    None => Err(ApiError::new(404, "Not found")),
+                }
+            }
+            _ => Err(ApiError::new(404, "Not found")),
+        }
+    }
+
+    fn log_in(&mut self, request: &mut Request) -> ApiResult {
+        let credentials: Credentials = read_json(request)?;
+        let logged_in = self
+            .store
+            .change(|database| database.log_in(&credentials.username, &credentials.password))?;
+
+        Ok(json(
+            200,
+            &serde_json::json!({ "token": logged_in.token, "user": logged_in.user }),
+        ))
+    }
+
+    fn log_out(&mut self, request: &Request) -> ApiResult {
+        if self.act_as(request).is_ok() {
+            self.store.change(|database| {
+                database.log_out();
+                Ok(())
+            })?;
+        }
+
+        Ok(Response::from_data(Vec::new()).with_status_code(204))
+    }
+
+    /// Acts on the data as the user the token of the request belongs to,
+    /// the `Database` keeps the sessions along with the data
+    fn act_as(&mut self, request: &Request) -> Result<AuthenticatedUser, ApiError> {
+        let unauthorized = || ApiError::new(401, "Log in to use the API");
+        let token = bearer_token(request).ok_or_else(unauthorized)?;
+        let username = self
+            .store
+            .database()
+            .session_username(&token)
+            .ok_or_else(unauthorized)?;
+
+        Ok(self.store.database_mut().resume(&username, &token)?)
+    }
+}
+
+/// Whether `request` goes to an endpoint of the API rather than a static
+/// file, preflight requests aside
+fn is_api(request: &Request) -> bool {
+    let first = request
+        .url()
+        .trim_start_matches('/')
+        .split(['/', '?'])
+        .next();
+
+    *request.method() != Method::Options
+        && matches!(first, Some("login" | "logout" | "session" | "persons"))
+}
+
+fn header(field: &str, value: &str) -> Header {
+    Header::from_bytes(field.as_bytes(), value.as_bytes()).expect("Invalid header")
+}
+
+fn json<T: Serialize>(status: u16, value: &T) -> Reply {
+    let body = serde_json::to_vec(value).expect("Unable to serialize a response");
+
+    Response::from_data(body)
+        .with_status_code(status)
+        .with_header(header("Content-Type", "application/json"))
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(request: &mut Request) -> Result<T, ApiError> {
+    let mut body = String::new();
+
+    request
+        .as_reader()
+        .take(MAX_BODY_LENGTH + 1)
+        .read_to_string(&mut body)?;
+
+    if body.len() as u64 > MAX_BODY_LENGTH {
+        return Err(ApiError::new(413, "The body is too large"));
+    }
+
+    serde_json::from_str(&body).map_err(|err| ApiError::new(400, &format!("Invalid body: {}", err)))
+}
+
+fn bearer_token(request: &Request) -> Option<String> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Authorization"))
+        .and_then(|header| header.value.as_str().strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string())
+}
+
+/// Version of the person the `If-Match` header asks to change, quoted like
+/// an etag or not
+fn if_match(request: &Request) -> Result<Option<u32>, ApiError> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("If-Match"))
+        .map(|header| {
+            header
+                .value
+                .as_str()
+                .trim()
+                .trim_matches('"')
+                .parse()
+                .map_err(|_| ApiError::new(400, "If-Match must be the version of the person"))
+        })
+        .transpose()
+}
+
+fn parse_id(id: &str) -> Result<u32, ApiError> {
+    id.parse()
+        .map_err(|_| ApiError::new(400, "The ID must be a whole number"))
+}
+
+/// Reads `text`, `match_case`, `fuzzy`, `sort`, `offset` and `limit` from the
+/// query string, `sort` is `id` or `name`, with a leading `-` to sort in
+/// descending order. Every person is returned when `limit` is missing
+fn persons_query(query: &str) -> Result<PersonsQuery, ApiError> {
+    let mut persons_query = PersonsQuery {
+        limit: usize::MAX,
+        ..PersonsQuery::default()
+    };
+    let invalid = |name: &str| ApiError::new(400, &format!("Invalid value of {}", name));
+
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = decode(value).ok_or_else(|| invalid(name))?;
+
+        match name {
+            "text" => persons_query.text = value,
+            "match_case" => persons_query.match_case = value == "true",
+            "fuzzy" => persons_query.fuzzy = value == "true",
+            "sort" => {
+                let ascending = !value.starts_with('-');
+                let column = match value.trim_start_matches('-') {
+                    "id" => SortColumn::Id,
+                    "name" => SortColumn::Name,
+                    _ => return Err(invalid(name)),
+                };
+
+                persons_query.sort = Some(PersonsSort { column, ascending });
+            }
+            "offset" => persons_query.offset = value.parse().map_err(|_| invalid(name))?,
+            "limit" => persons_query.limit = value.parse().map_err(|_| invalid(name))?,
+            _ => {}
+        }
+    }
+
+    Ok(persons_query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiny_http::TestRequest;
+
+    /// An `Api` over the seed data, kept in a fresh temporary directory
+    fn api(name: &str) -> Api {
+        let dir = std::env::temp_dir().join(format!("server-api-{}-{}", name, std::process::id()));
+
+        std::fs::create_dir_all(&dir).unwrap();
+        let _ = std::fs::remove_file(dir.join("data.json"));
+
+        reopen(name)
+    }
+
+    /// Another `Api` over the data of `api(name)`, like restarting it
+    fn reopen(name: &str) -> Api {
+        let dir = std::env::temp_dir().join(format!("server-api-{}-{}", name, std::process::id()));
+
+        Api::new(
+            FileStore::open(&dir.join("data.json")).unwrap(),
+            StaticFiles::new(dir.join("static")),
+            Mock::default(),
+        )
+    }
+
+    /// Status and body of the reply to `method` at `path`
+    fn call(
+        api: &mut Api,
+        method: Method,
+        path: &str,
+        token: &str,
+        body: &'static str,
+    ) -> (u16, String) {
+        let mut request: Request = TestRequest::new()
+            .with_method(method)
+            .with_path(path)
+            .with_header(header("Authorization", &format!("Bearer {}", token)))
+            .with_body(body)
+            .into();
+        let reply = api.answer(&mut request);
+
+        (reply.status_code().0, body_of(reply))
+    }
+
+    fn log_in_as_root(api: &mut Api) -> String {
+        let (status, body) = call(
+            api,
+            Method::Post,
+            "/login",
+            "",
+            r#"{"username": "root", "password": "root"}"#,
+        );
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+        assert_eq!(status, 200);
+        body["token"].as_str().unwrap().to_string()
+    }
+
+    fn header_value(reply: &Reply, name: &'static str) -> Option<String> {
+        reply
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv(name))
+            .map(|header| header.value.to_string())
+    }
+
+    #[test]
+    fn preflights_allow_the_headers_the_app_sends() {
+        let mut api = api("preflight");
+        let mut request: Request = TestRequest::new()
+            .with_method(Method::Options)
+            .with_path("/persons/2")
+            .into();
+        let reply = api.answer(&mut request);
+        let allowed = header_value(&reply, "Access-Control-Allow-Headers").unwrap_or_default();
+        let allowed: Vec<&str> = allowed.split(',').map(str::trim).collect();
+
+        assert_eq!(reply.status_code().0, 204);
+        assert_eq!(
+            header_value(&reply, "Access-Control-Allow-Origin").as_deref(),
+            Some("*")
+        );
+        assert!(header_value(&reply, "Access-Control-Allow-Methods")
+            .unwrap_or_default()
+            .contains("PUT"));
+
+        for name in &["Authorization", "Content-Type", "If-Match"] {
+            assert!(allowed.contains(name), "{}", name);
+        }
+    }
+
+    fn body_of(reply: Reply) -> String {
+        let mut body = String::new();
+
+        reply.into_reader().read_to_string(&mut body).unwrap();
+        body
+    }
+
+    #[test]
+    fn log_in_fails_alike_for_unknown_users_and_wrong_passwords() {
+        let mut api = api("log-in");
+        let unknown = call(
+            &mut api,
+            Method::Post,
+            "/login",
+            "",
+            r#"{"username": "nobody", "password": "root"}"#,
+        );
+        let wrong = call(
+            &mut api,
+            Method::Post,
+            "/login",
+            "",
+            r#"{"username": "root", "password": "wrong"}"#,
+        );
+
+        assert_eq!(unknown.0, 401);
+        assert_eq!(unknown, wrong);
+    }
+
+    #[test]
+    fn changes_that_can_not_be_written_are_undone() {
+        let mut api = api("unwritable");
+        let token = log_in_as_root(&mut api);
+        let dir =
+            std::env::temp_dir().join(format!("server-api-unwritable-{}", std::process::id()));
+
+        // The temporary file the data is written to first can't be created
+        std::fs::create_dir_all(dir.join("data.tmp")).unwrap();
+
+        let (status, _) = call(
+            &mut api,
+            Method::Post,
+            "/persons",
+            &token,
+            r#"{"id": 0, "name": "Zelda"}"#,
+        );
+        let (_, persons) = call(&mut api, Method::Get, "/persons", &token, "");
+
+        std::fs::remove_dir(dir.join("data.tmp")).unwrap();
+
+        assert_eq!(status, 500);
+        assert!(!persons.contains("Zelda"), "{}", persons);
+    }
+
+    #[test]
+    fn sessions_survive_a_restart_but_not_a_log_out() {
+        let token = log_in_as_root(&mut api("sessions"));
+        let mut api = reopen("sessions");
+
+        assert_eq!(call(&mut api, Method::Get, "/session", &token, "").0, 200);
+        assert_eq!(call(&mut api, Method::Get, "/session", "other", "").0, 401);
+        assert_eq!(call(&mut api, Method::Post, "/logout", &token, "").0, 204);
+        assert_eq!(call(&mut api, Method::Get, "/session", &token, "").0, 401);
+    }
+
+    fn request_with(name: &str, value: &str) -> Request {
+        TestRequest::new().with_header(header(name, value)).into()
+    }
+
+    #[test]
+    fn is_api_leaves_out_static_files_and_preflights() {
+        let request = |method, url: &str| -> Request {
+            TestRequest::new().with_method(method).with_path(url).into()
+        };
+
+        assert!(is_api(&request(Method::Post, "/login")));
+        assert!(is_api(&request(Method::Get, "/persons?text=ana")));
+        assert!(is_api(&request(Method::Put, "/persons/2")));
+        assert!(!is_api(&request(Method::Options, "/persons/2")));
+        assert!(!is_api(&request(Method::Get, "/")));
+        assert!(!is_api(&request(Method::Get, "/personsapp.js")));
+    }
+
+    #[test]
+    fn persons_query_reads_every_parameter() {
+        let query = persons_query("text=Ana+Mar%C3%ADa&fuzzy=true&sort=-name&offset=10&limit=5")
+            .unwrap_or_else(|err| panic!("{}", err.message));
+
+        assert_eq!(query.text, "Ana María");
+        assert!(query.fuzzy);
+        assert!(!query.match_case);
+        assert_eq!(
+            query.sort,
+            Some(PersonsSort {
+                column: SortColumn::Name,
+                ascending: false,
+            })
+        );
+        assert_eq!((query.offset, query.limit), (10, 5));
+    }
+
+    #[test]
+    fn persons_query_defaults_to_every_person() {
+        let query = persons_query("").unwrap_or_else(|err| panic!("{}", err.message));
+
+        assert_eq!(query.text, "");
+        assert_eq!(query.sort, None);
+        assert_eq!((query.offset, query.limit), (0, usize::MAX));
+
+        let query =
+            persons_query("sort=id&unknown=1").unwrap_or_else(|err| panic!("{}", err.message));
+
+        assert_eq!(
+            query.sort,
+            Some(PersonsSort {
+                column: SortColumn::Id,
+                ascending: true,
+            })
+        );
+    }
+
+    #[test]
+    fn persons_query_rejects_invalid_values() {
+        for query in &[
+            "offset=-1",
+            "offset=ten",
+            "limit=",
+            "sort=email",
+            "text=%E9",
+        ] {
+            let err = persons_query(query).expect_err(query);
+
+            assert_eq!(err.status, 400, "{}", query);
+        }
+    }
+
+    #[test]
+    fn if_match_reads_quoted_and_unquoted_versions() {
+        assert_eq!(
+            if_match(&request_with("If-Match", "\"3\"")).ok(),
+            Some(Some(3))
+        );
+        assert_eq!(
+            if_match(&request_with("if-match", " 7 ")).ok(),
+            Some(Some(7))
+        );
+        assert_eq!(if_match(&TestRequest::new().into()).ok(), Some(None));
+        assert_eq!(
+            if_match(&request_with("If-Match", "*"))
+                .err()
+                .map(|err| err.status),
+            Some(400)
+        );
+    }
+
+    #[test]
+    fn person_update_tells_a_missing_version_apart() {
+        let update: PersonUpdate =
+            serde_json::from_str(r#"{"id": 2, "name": "June", "version": 4}"#).unwrap();
+
+        assert_eq!(update.version, Some(4));
+        assert_eq!(update.person.name, "June");
+
+        let update: PersonUpdate = serde_json::from_str(r#"{"id": 2, "name": "June"}"#).unwrap();
+
+        assert_eq!(update.version, None);
+    }
+
+    #[test]
+    fn read_json_limits_the_body() {
+        let mut request: Request = TestRequest::new().with_body(r#"{"id": 3}"#).into();
+        let inserted: Result<serde_json::Value, _> = read_json(&mut request);
+
+        assert_eq!(inserted.ok(), Some(serde_json::json!({ "id": 3 })));
+
+        let body = format!("\"{}\"", "a".repeat(MAX_BODY_LENGTH as usize));
+        let mut request: Request = TestRequest::new()
+            .with_body(Box::leak(body.into_boxed_str()))
+            .into();
+        let result: Result<String, _> = read_json(&mut request);
+
+        assert_eq!(result.err().map(|err| err.status), Some(413));
+    }
+}
@@ -0,0 +1,107 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub const USAGE: &str = "\
+Usage: server [--address HOST:PORT] [--data FILE] [--static DIR]
+              [--delay MS] [--fail-every N]
+
+  --address     Address to listen on, 127.0.0.1:7878 by default
+  --data        JSON file the data is kept in, auth-data.json by default
+  --static      Directory of the built auth app, ../auth/static by default
+  --delay       Milliseconds to wait before answering each API request
+  --fail-every  Fail every Nth API request with 503";
+
+/// Settings of the server, taken from the command line
+#[derive(Debug)]
+pub struct Config {
+    pub address: String,
+    pub data_file: PathBuf,
+    pub static_dir: PathBuf,
+    pub mock: Mock,
+}
+
+/// Misbehaviour of the API, to try the app against a slow or unreliable
+/// server. The static files are always served right away
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Mock {
+    pub delay: Duration,
+    pub fail_every: Option<u32>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            address: "127.0.0.1:7878".to_string(),
+            data_file: PathBuf::from("auth-data.json"),
+            static_dir: PathBuf::from("../auth/static"),
+            mock: Mock::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn from_args<I>(args: I) -> Result<Config, String>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut config = Config::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("Missing the value of {}", arg))
+            };
+
+            match arg.as_str() {
+                "--address" => config.address = value()?,
+                "--data" => config.data_file = PathBuf::from(value()?),
+                "--static" => config.static_dir = PathBuf::from(value()?),
+                "--delay" => {
+                    let millis = number(&arg, &value()?)?;
+
+                    config.mock.delay = Duration::from_millis(millis.into());
+                }
+                "--fail-every" => match number(&arg, &value()?)? {
+                    0 => return Err("The value of --fail-every must be above 0".to_string()),
+                    every => config.mock.fail_every = Some(every),
+                },
+                _ => return Err(format!("Unknown argument {}", arg)),
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+fn number(arg: &str, value: &str) -> Result<u32, String> {
+    value
+        .parse()
+        .map_err(|_| format!("The value of {} must be a whole number", arg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_args(args: &[&str]) -> Result<Config, String> {
+        Config::from_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn reads_the_mock_settings() {
+        let config = from_args(&["--delay", "1500", "--fail-every", "3"]).unwrap();
+
+        assert_eq!(config.mock.delay, Duration::from_millis(1500));
+        assert_eq!(config.mock.fail_every, Some(3));
+        assert_eq!(from_args(&[]).unwrap().mock.fail_every, None);
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert!(from_args(&["--delay"]).is_err());
+        assert!(from_args(&["--delay", "soon"]).is_err());
+        assert!(from_args(&["--fail-every", "0"]).is_err());
+        assert!(from_args(&["--verbose"]).is_err());
+    }
+}
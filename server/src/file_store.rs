@@ -0,0 +1,72 @@
+use auth::database::{Database, DbError, DbResult};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A `Database` kept in a JSON file, in the same format the browser
+/// backends use, written out after every change
+#[derive(Debug)]
+pub struct FileStore {
+    database: Database,
+    path: PathBuf,
+}
+
+impl FileStore {
+    /// Reads the data in `path`, or starts with the seed data when the
+    /// file doesn't exist yet
+    pub fn open(path: &Path) -> io::Result<FileStore> {
+        let database = match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Database::new(),
+            Err(err) => return Err(err),
+        };
+        let store = FileStore {
+            database,
+            path: path.to_path_buf(),
+        };
+
+        store.save()?;
+
+        Ok(store)
+    }
+
+    pub fn database(&self) -> &Database {
+        &self.database
+    }
+
+    pub fn database_mut(&mut self) -> &mut Database {
+        &mut self.database
+    }
+
+    /// Applies `mutation` to the data and writes it out, the data is left
+    /// as it was when the file can't be written, so it is never served
+    pub fn change<T, F>(&mut self, mutation: F) -> DbResult<T>
+    where
+        F: FnOnce(&mut Database) -> DbResult<T>,
+    {
+        let before = self.database.clone();
+        let value = mutation(&mut self.database)?;
+
+        if let Err(err) = self.save() {
+            self.database = before;
+            return Err(DbError::Storage(format!(
+                "Unable to write {}: {}",
+                self.path.display(),
+                err
+            )));
+        }
+
+        Ok(value)
+    }
+
+    /// Writes the data to a temporary file first, so a failed write never
+    /// leaves the file half written
+    fn save(&self) -> io::Result<()> {
+        let json = serde_json::to_string(&self.database)?;
+        let temporary = self.path.with_extension("tmp");
+
+        fs::write(&temporary, json)?;
+        fs::rename(&temporary, &self.path)
+    }
+}
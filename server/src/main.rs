@@ -0,0 +1,43 @@
+use std::process;
+
+use crate::api::Api;
+use crate::config::Config;
+use crate::file_store::FileStore;
+use crate::static_files::StaticFiles;
+
+mod api;
+mod config;
+mod file_store;
+mod static_files;
+
+fn main() {
+    let config = Config::from_args(std::env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("{}\n\n{}", err, config::USAGE);
+        process::exit(2);
+    });
+
+    let store = FileStore::open(&config.data_file).unwrap_or_else(|err| {
+        eprintln!("Unable to open {}: {}", config.data_file.display(), err);
+        process::exit(1);
+    });
+
+    let server = tiny_http::Server::http(&config.address).unwrap_or_else(|err| {
+        eprintln!("Unable to listen on {}: {}", config.address, err);
+        process::exit(1);
+    });
+
+    println!(
+        "Serving {} and the data in {} on http://{}",
+        config.static_dir.display(),
+        config.data_file.display(),
+        config.address
+    );
+
+    let mut api = Api::new(store, StaticFiles::new(config.static_dir), config.mock);
+
+    // Requests are handled one at a time, the `Database` acts as a single
+    // user at a time
+    for request in server.incoming_requests() {
+        api.handle(request);
+    }
+}
@@ -0,0 +1,104 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Files of the built `auth` app, served for every request outside the API
+#[derive(Debug)]
+pub struct StaticFiles {
+    root: PathBuf,
+}
+
+impl StaticFiles {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// Contents and content type of the file at `path` of the URL, `/`
+    /// being `index.html`. Paths leaving the root are never served
+    pub fn read(&self, path: &str) -> Option<(Vec<u8>, &'static str)> {
+        let relative = Path::new(path.trim_start_matches('/'));
+
+        if relative
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_)))
+        {
+            return None;
+        }
+
+        let mut file = self.root.join(relative);
+
+        if file.is_dir() {
+            file.push("index.html");
+        }
+
+        let content = fs::read(&file).ok()?;
+
+        Some((content, content_type(&file)))
+    }
+}
+
+fn content_type(file: &Path) -> &'static str {
+    match file.extension().and_then(|extension| extension.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("js") => "text/javascript",
+        Some("wasm") => "application/wasm",
+        Some("css") => "text/css",
+        Some("json") => "application/json",
+        Some("ts") => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A root with `index.html`, `app.js` and `docs/index.html`, next to a
+    /// `secret.txt` file outside of it
+    fn static_files(name: &str) -> StaticFiles {
+        let base =
+            std::env::temp_dir().join(format!("server-static-{}-{}", name, std::process::id()));
+        let root = base.join("static");
+
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::write(base.join("secret.txt"), "secret").unwrap();
+        fs::write(root.join("index.html"), "<html>").unwrap();
+        fs::write(root.join("app.js"), "run()").unwrap();
+        fs::write(root.join("docs").join("index.html"), "docs").unwrap();
+
+        StaticFiles::new(root)
+    }
+
+    #[test]
+    fn serves_files_and_directory_indexes() {
+        let files = static_files("serves");
+
+        assert_eq!(
+            files.read("/"),
+            Some((b"<html>".to_vec(), "text/html; charset=utf-8"))
+        );
+        assert_eq!(
+            files.read("/app.js"),
+            Some((b"run()".to_vec(), "text/javascript"))
+        );
+        assert_eq!(
+            files.read("/docs/").map(|(content, _)| content),
+            Some(b"docs".to_vec())
+        );
+        assert_eq!(files.read("/missing.js"), None);
+    }
+
+    #[test]
+    fn never_leaves_the_root() {
+        let files = static_files("root");
+
+        for path in &[
+            "/../secret.txt",
+            "/docs/../../secret.txt",
+            "/./index.html",
+            "//../secret.txt",
+            "/docs/..",
+        ] {
+            assert_eq!(files.read(path), None, "{}", path);
+        }
+    }
+}